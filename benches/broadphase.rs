@@ -0,0 +1,112 @@
+//====================================================================
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use hecs::World;
+use hecs_engine::common::Transform;
+use hecs_game::bench_support::{
+    self, CharacterCollisionBundle, CollisionShape, Gravity, PhysicsHandler, StaticCollisionType,
+    Velocity,
+};
+
+//====================================================================
+
+const BLOCK_SIZE: f32 = 20.;
+const MOVERS_PER_SIDE: i32 = 4;
+const TICKS: u32 = 600;
+const TICK: f32 = 1. / 60.;
+
+// Times full PhysicsHandler ticks with characters sliding over a floor of
+// static blocks. Movers stay over the same few blocks in both worlds, so any
+// difference is down to how many static colliders there are to search through.
+// Run with `cargo bench`.
+fn main() {
+    let small = run_world(7);
+    let large = run_world(70);
+
+    println!(
+        "slowdown:     {:.2}x for {}x the static colliders",
+        large.as_secs_f64() / small.as_secs_f64(),
+        (70 * 70) / (7 * 7)
+    );
+}
+
+//====================================================================
+
+fn run_world(blocks_per_side: i32) -> Duration {
+    let mut world = World::new();
+    let mut physics = PhysicsHandler::new(1. / TICK, 1);
+
+    (0..blocks_per_side)
+        .flat_map(|x| (0..blocks_per_side).map(move |z| (x, z)))
+        .for_each(|(x, z)| {
+            world.spawn((
+                Transform::from_translation(glam::vec3(
+                    x as f32 * BLOCK_SIZE,
+                    -BLOCK_SIZE / 2.,
+                    z as f32 * BLOCK_SIZE,
+                )),
+                StaticCollisionType,
+                CollisionShape::Box {
+                    half_width: BLOCK_SIZE / 2.,
+                    half_height: BLOCK_SIZE / 2.,
+                    half_depth: BLOCK_SIZE / 2.,
+                },
+            ));
+        });
+
+    // Slow enough to stay inside the small world for every tick
+    let movers = (0..MOVERS_PER_SIDE)
+        .flat_map(|x| (0..MOVERS_PER_SIDE).map(move |z| (x, z)))
+        .map(|(x, z)| {
+            let entity = world.spawn(CharacterCollisionBundle::from_shape(CollisionShape::Box {
+                half_width: 5.,
+                half_height: 5.,
+                half_depth: 5.,
+            }));
+            world
+                .insert(
+                    entity,
+                    (
+                        Transform::from_translation(glam::vec3(
+                            10. + x as f32 * 25.,
+                            6.,
+                            10. + z as f32 * 25.,
+                        )),
+                        Velocity(glam::vec3(4., 0., 2.5)),
+                        Gravity(glam::vec3(0., -400., 0.)),
+                    ),
+                )
+                .unwrap();
+
+            entity
+        })
+        .collect::<Vec<_>>();
+
+    // Prepares every static collider and builds the grid, which isn't part of a normal tick
+    bench_support::tick(&mut physics, &mut world, TICK);
+
+    let start = Instant::now();
+    (0..TICKS).for_each(|_| bench_support::tick(&mut physics, &mut world, TICK));
+    let elapsed = start.elapsed();
+
+    // Anything that fell through the floor would have skipped its collision checks
+    movers.iter().for_each(|entity| {
+        let transform = world.get::<&Transform>(*entity).unwrap();
+        assert!(transform.translation.y > 0., "mover fell through the floor");
+    });
+    black_box(&world);
+
+    println!(
+        "{:>5} static colliders: {:?} per tick",
+        blocks_per_side * blocks_per_side,
+        elapsed / TICKS
+    );
+
+    elapsed
+}
+
+//====================================================================
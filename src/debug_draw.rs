@@ -0,0 +1,94 @@
+//====================================================================
+
+use hecs::Entity;
+use hecs_engine::{
+    common::{GlobalTransform, Transform},
+    engine::State,
+    pipelines::model_renderer::Model,
+};
+
+use crate::{
+    physics::{DebugColliderKind, DebugLine, PhysicsHandler},
+    Resources,
+};
+
+//====================================================================
+
+const LINE_THICKNESS: f32 = 0.5;
+
+const STATIC_COLOR: [f32; 4] = [0.2, 0.6, 1., 1.];
+const KINEMATIC_COLOR: [f32; 4] = [0.7, 0.3, 1., 1.];
+const DYNAMIC_COLOR: [f32; 4] = [0.2, 1., 0.3, 1.];
+const TRIGGER_COLOR: [f32; 4] = [1., 0.6, 0.1, 1.];
+const TOUCHING_COLOR: [f32; 4] = [1., 0.15, 0.15, 1.];
+
+//====================================================================
+
+// Draws collider wireframes as thin stretched cubes. Line entities are reused
+// between frames and only spawned or despawned when the number of lines changes.
+#[derive(Default)]
+pub struct PhysicsDebugDraw {
+    lines: Vec<Entity>,
+}
+
+impl PhysicsDebugDraw {
+    pub fn update(&mut self, state: &mut State, physics: &PhysicsHandler, resources: &Resources) {
+        let lines = physics.debug_lines(state);
+
+        while self.lines.len() < lines.len() {
+            let entity = state.world_mut().spawn((
+                Transform::default(),
+                GlobalTransform::default(),
+                Model {
+                    meshes: vec![(resources.cube.clone(), resources.texture.clone())],
+                    color: [1., 1., 1., 1.],
+                    scale: glam::Vec3::ONE,
+                },
+            ));
+
+            self.lines.push(entity);
+        }
+
+        self.lines.drain(lines.len()..).for_each(|entity| {
+            state.world_mut().despawn(entity).ok();
+        });
+
+        self.lines
+            .iter()
+            .zip(lines.iter())
+            .for_each(|(entity, line)| {
+                let entity = state.world().entity(*entity).unwrap();
+                let mut transform = entity.get::<&mut Transform>().unwrap();
+                let mut model = entity.get::<&mut Model>().unwrap();
+
+                let offset = line.end - line.start;
+                let direction = offset.try_normalize().unwrap_or(glam::Vec3::Z);
+
+                transform.translation = (line.start + line.end) * 0.5;
+                transform.rotation = glam::Quat::from_rotation_arc(glam::Vec3::Z, direction);
+                transform.scale = glam::Vec3::ONE;
+
+                model.scale = glam::vec3(LINE_THICKNESS, LINE_THICKNESS, offset.length());
+                model.color = line_color(line);
+            });
+    }
+
+    pub fn clear(&mut self, state: &mut State) {
+        self.lines.drain(..).for_each(|entity| {
+            state.world_mut().despawn(entity).ok();
+        });
+    }
+}
+
+#[inline]
+fn line_color(line: &DebugLine) -> [f32; 4] {
+    match (line.touching, line.kind) {
+        (true, _) => TOUCHING_COLOR,
+        (false, DebugColliderKind::Static) => STATIC_COLOR,
+        (false, DebugColliderKind::Kinematic) => KINEMATIC_COLOR,
+        (false, DebugColliderKind::Dynamic) => DYNAMIC_COLOR,
+        (false, DebugColliderKind::Trigger) => TRIGGER_COLOR,
+    }
+}
+
+//====================================================================
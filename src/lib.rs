@@ -2,6 +2,7 @@
 
 use std::sync::Arc;
 
+use debug_draw::PhysicsDebugDraw;
 use hecs::{Entity, EntityBuilder};
 use hecs_engine::{
     engine::tools::KeyCode,
@@ -19,10 +20,29 @@ use player::PlayerState;
 use wasm_bindgen::prelude::*;
 
 pub(crate) mod camera;
+pub(crate) mod debug_draw;
 pub(crate) mod physics;
 pub(crate) mod player;
+pub(crate) mod terrain;
 pub(crate) mod tools;
 
+// Only for benches/, which can't reach the crate's own modules
+#[doc(hidden)]
+pub mod bench_support {
+    use hecs::World;
+
+    pub use crate::physics::{
+        CharacterCollisionBundle, CollisionShape, Gravity, PhysicsHandler, StaticCollisionType,
+        Velocity,
+    };
+
+    // Ticks without needing a State, which only the engine's runner can make
+    #[inline]
+    pub fn tick(physics: &mut PhysicsHandler, world: &mut World, delta_seconds: f32) {
+        physics.tick(world, delta_seconds);
+    }
+}
+
 //====================================================================
 
 #[cfg(target_arch = "wasm32")]
@@ -42,14 +62,16 @@ pub fn run() {
 }
 
 pub struct Game {
-    _res: Resources,
+    res: Resources,
     player_state: PlayerState,
     physics: PhysicsHandler,
+    physics_debug: PhysicsDebugDraw,
 
     camera: Entity,
 
     cursor_locked: bool,
     camera_debug: bool,
+    physics_debug_enabled: bool,
 }
 
 pub struct Resources {
@@ -96,12 +118,14 @@ impl App for Game {
         let physics = PhysicsHandler::default();
 
         Self {
-            _res: res,
+            res,
             player_state,
             physics,
+            physics_debug: PhysicsDebugDraw::default(),
             camera,
             cursor_locked,
             camera_debug: false,
+            physics_debug_enabled: false,
         }
     }
 
@@ -140,6 +164,14 @@ impl App for Game {
             };
         }
 
+        if state.keys().just_pressed(KeyCode::F3) {
+            self.physics_debug_enabled = !self.physics_debug_enabled;
+
+            if !self.physics_debug_enabled {
+                self.physics_debug.clear(state);
+            }
+        }
+
         if self.camera_debug {
             camera::debug_move_camera(state);
         }
@@ -149,6 +181,10 @@ impl App for Game {
 
         self.physics.tick_physics(state);
 
+        if self.physics_debug_enabled {
+            self.physics_debug.update(state, &self.physics, &self.res);
+        }
+
         tools::process_follow_entity(state);
     }
 }
@@ -181,10 +217,11 @@ fn spawn_world(state: &mut State, resources: &Resources) {
             color: [0.3, 0.3, 0.3, 1.],
         },
         StaticCollisionType,
+        // Sprite lies in local XY so the collider's thin axis is local Z
         CollisionShape::Box {
             half_width: 250.,
-            half_height: 5.,
-            half_depth: 250.,
+            half_height: 250.,
+            half_depth: 5.,
         },
     ));
 
@@ -215,6 +252,9 @@ fn spawn_world(state: &mut State, resources: &Resources) {
             half_depth: 10.,
         },
     ));
+
+    // Just off the far edge of the floor, level with the top of its collider
+    terrain::spawn_terrain(state, resources, glam::vec3(0., -35., 410.));
 }
 
 //====================================================================
@@ -1,13 +1,44 @@
 //====================================================================
 
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
 use hecs::{Bundle, ChangeTracker, Entity, World};
-use hecs_engine::{common::Transform, engine::State};
+use hecs_engine::{
+    common::Transform,
+    engine::{spatial::LocalTransform, State},
+};
+
+use broadphase::{Aabb, SpatialGrid};
+use collision::{Contact, PreppedCollisionShape};
+pub use collision::{Heightfield, TriMesh};
+pub use collision_events::CollisionEvent;
+pub use debug::{DebugColliderKind, DebugLine};
+// For game code, nothing places force volumes yet
+#[allow(unused_imports)]
+pub use force_volumes::ForceVolume;
+pub use queries::{QueryFilter, QueryHit};
+pub use triggers::TriggerEvent;
+
+pub mod broadphase;
+mod collision;
+mod collision_events;
+mod debug;
+mod force_volumes;
+mod queries;
+mod rigid_body;
+mod triggers;
 
 //====================================================================
 
 pub enum MovementAction {
     Move((f32, f32)),
     Jump,
+    JumpRelease,
+    Crouch,
+    CrouchRelease,
 }
 
 //====================================================================
@@ -18,19 +49,109 @@ pub struct CharacterController {
 
 pub struct Velocity(pub glam::Vec3);
 pub struct Accel(pub f32);
-pub struct DeaccelDampingFactor(pub f32);
 pub struct JumpImpulse(pub f32);
 pub struct Gravity(pub glam::Vec3);
 
 pub struct Grounded;
 
+// Which way is up on whatever a grounded entity is standing on. Kept up to date
+// alongside Grounded so slopes and terrain can be followed by game code.
+#[allow(dead_code)]
+pub struct GroundNormal(pub glam::Vec3);
+
+// Rate horizontal velocity decays at on the ground, per second. Velocity is
+// multiplied by e^(-damping * seconds) so it's the same whatever the tick rate.
+pub struct Damping(pub f32);
+
+// Horizontal speed is clamped to this after accelerating
+pub struct MaxSpeed(pub f32);
+
+// Upward velocity is multiplied by this when jump is released early, for variable jump height
+pub struct JumpCutFactor(pub f32);
+
+// Extra jumps allowed before landing again
+pub struct AirJumps(pub u32);
+
+// Used instead of Accel and Damping while airborne
+pub struct AirControl {
+    pub accel: f32,
+    pub damping: f32,
+}
+
+// Height is the fraction of the standing collider kept while crouched, feet staying
+// where they are. Speed scales acceleration while crouched. Only boxes and capsules crouch.
+pub struct CrouchControl {
+    pub height: f32,
+    pub speed: f32,
+}
+
+// Seconds after walking off a ledge that a jump is still allowed
+pub struct CoyoteTime(pub f32);
+
+// Seconds a jump pressed while airborne is remembered, so it happens on landing
+pub struct JumpBuffer(pub f32);
+
+// Added automatically to anything with a CharacterController
+struct JumpTimers {
+    since_grounded: f32,
+    buffered: f32,
+    air_jumps_used: u32,
+    jumping: bool,
+}
+
+// Added while crouched. Standing back up waits until there's room for the standing shape.
+struct Crouched {
+    standing: CollisionShape,
+    drop: f32,
+    speed: f32,
+    standing_up: bool,
+}
+
+// Tallest ledge a character will step up onto. Also used as the distance to
+// snap down to the ground when walking down slopes.
+pub struct MaxStepHeight(pub f32);
+
+// Steepest slope a character can walk up, in radians. Anything steeper is slid down.
+pub struct MaxSlopeAngle(pub f32);
+
+// Opt in to swept collision for fast movers that could skip through thin colliders
+pub struct ContinuousCollision;
+
+// Physics runs at a fixed rate so the rendered translation and rotation are blended
+// between the last two physics steps. Added automatically to anything with a Velocity.
+pub struct PhysicsInterpolation {
+    previous: glam::Vec3,
+    current: glam::Vec3,
+    rendered: glam::Vec3,
+    previous_rotation: glam::Quat,
+    current_rotation: glam::Quat,
+    rendered_rotation: glam::Quat,
+}
+
+impl PhysicsInterpolation {
+    #[inline]
+    fn new(transform: &Transform) -> Self {
+        Self {
+            previous: transform.translation,
+            current: transform.translation,
+            rendered: transform.translation,
+            previous_rotation: transform.rotation,
+            current_rotation: transform.rotation,
+            rendered_rotation: transform.rotation,
+        }
+    }
+}
+
 #[derive(Bundle)]
 pub struct CharacterMovementBundle {
     controller: CharacterController,
     velocity: Velocity,
     accel: Accel,
-    deaccel: DeaccelDampingFactor,
+    damping: Damping,
     jump: JumpImpulse,
+    jump_cut: JumpCutFactor,
+    coyote_time: CoyoteTime,
+    jump_buffer: JumpBuffer,
     gravity: Gravity,
 }
 
@@ -42,11 +163,75 @@ impl Default for CharacterMovementBundle {
             },
             velocity: Velocity(glam::Vec3::ZERO),
             accel: Accel(1300.),
-            deaccel: DeaccelDampingFactor(0.9),
+            damping: Damping(6.3),
             jump: JumpImpulse(200.),
+            jump_cut: JumpCutFactor(0.5),
+            coyote_time: CoyoteTime(0.1),
+            jump_buffer: JumpBuffer(0.1),
+            gravity: Gravity(glam::vec3(0., -400., 0.)),
+        }
+    }
+}
+
+//====================================================================
+
+// Tumbles and bounces off things under impulses rather than being driven by a
+// CharacterController. Its collider rotates with it, unlike a character's.
+pub struct RigidBody;
+
+pub struct AngularVelocity(pub glam::Vec3);
+pub struct Mass(pub f32);
+
+// How much of the impact speed is kept when bouncing, 0 - 1
+pub struct Restitution(pub f32);
+
+// Coulomb friction coefficient. Can also be put on statics to change their surface,
+// otherwise they use a default.
+pub struct Friction(pub f32);
+
+#[derive(Bundle)]
+pub struct RigidBodyBundle {
+    rigid_body: RigidBody,
+    velocity: Velocity,
+    angular_velocity: AngularVelocity,
+    mass: Mass,
+    restitution: Restitution,
+    friction: Friction,
+    gravity: Gravity,
+    hits: CollisionHits,
+    dynamic: DynamicCollisionType,
+    shape: CollisionShape,
+}
+
+// Nothing in the game spawns rigid bodies yet
+#[allow(dead_code)]
+impl RigidBodyBundle {
+    pub fn new(shape: CollisionShape, mass: f32) -> Self {
+        Self {
+            rigid_body: RigidBody,
+            velocity: Velocity(glam::Vec3::ZERO),
+            angular_velocity: AngularVelocity(glam::Vec3::ZERO),
+            mass: Mass(mass),
+            restitution: Restitution(0.2),
+            friction: Friction(0.5),
             gravity: Gravity(glam::vec3(0., -400., 0.)),
+            hits: CollisionHits::default(),
+            dynamic: DynamicCollisionType,
+            shape,
         }
     }
+
+    #[inline]
+    pub fn with_restitution(mut self, restitution: f32) -> Self {
+        self.restitution = Restitution(restitution);
+        self
+    }
+
+    #[inline]
+    pub fn with_friction(mut self, friction: f32) -> Self {
+        self.friction = Friction(friction);
+        self
+    }
 }
 
 //====================================================================
@@ -56,18 +241,42 @@ pub struct CollisionHits {
     hits: Vec<(Entity, CollisionDirection)>,
 }
 
+// Only boxes and the terrain's heightfield are spawned by the game so far
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum CollisionShape {
     Box {
         half_width: f32,
         half_height: f32,
         half_depth: f32,
     },
+    Sphere {
+        radius: f32,
+    },
+    // Upright capsule - half_height includes the rounded caps
+    Capsule {
+        radius: f32,
+        half_height: f32,
+    },
+    // Exact level geometry, for statics only
+    TriMesh(Arc<TriMesh>),
+    // Terrain, also for statics only
+    Heightfield(Arc<Heightfield>),
+    // Several shapes acting as one collider, each offset from the entity's origin
+    Compound(Vec<(glam::Vec3, CollisionShape)>),
 }
 
 //--------------------------------------------------
 
+// Moves and is pushed out of things an axis at a time, so its collider stays upright
+// and axis aligned whatever the Transform's rotation - character controller or not.
+// Add a RigidBody for a collider that turns with the entity.
 pub struct DynamicCollisionType;
 
+// How hard a dynamic entity is to push around by other dynamic entities.
+// Entities without one are treated as having a weight of 1.
+pub struct PushWeight(pub f32);
+
 #[derive(Bundle)]
 pub struct CharacterCollisionBundle {
     hits: CollisionHits,
@@ -100,6 +309,47 @@ impl CharacterCollisionBundle {
     }
 }
 
+//--------------------------------------------------
+
+// Two entities only collide if each is a member of a layer the other filters for.
+// Entities without one are members of, and collide with, every layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CollisionLayers {
+    pub membership: u32,
+    pub filter: u32,
+}
+
+impl Default for CollisionLayers {
+    #[inline]
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl CollisionLayers {
+    pub const ALL: Self = Self::new(u32::MAX, u32::MAX);
+    #[allow(dead_code)]
+    pub const NONE: Self = Self::new(0, 0);
+
+    #[inline]
+    pub const fn new(membership: u32, filter: u32) -> Self {
+        Self { membership, filter }
+    }
+
+    #[inline]
+    pub fn interacts_with(&self, other: &CollisionLayers) -> bool {
+        self.membership & other.filter != 0 && other.membership & self.filter != 0
+    }
+
+    #[inline]
+    fn of(world: &World, entity: Entity) -> Self {
+        world
+            .get::<&CollisionLayers>(entity)
+            .map(|layers| *layers)
+            .unwrap_or_default()
+    }
+}
+
 //====================================================================
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -108,6 +358,123 @@ pub struct StaticCollisionType;
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TriggerCollisionType;
 
+// Collides like a static but is moved by game code. Pushes dynamic entities out
+// of its way and carries any standing on top of it along with it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KinematicCollisionType;
+
+// Where a kinematic entity was as of the last step
+#[derive(Clone, Copy)]
+struct KinematicMotion {
+    translation: glam::Vec3,
+    rotation: glam::Quat,
+}
+
+impl KinematicMotion {
+    #[inline]
+    fn from_transform(transform: &Transform) -> Self {
+        Self {
+            translation: transform.translation,
+            rotation: transform.rotation,
+        }
+    }
+}
+
+// What a prepped collider's placement was built from, kept on the collider and on
+// everything it's attached to so moves can be spotted without a full scan
+#[derive(Clone, Copy, PartialEq)]
+struct TrackedPlacement {
+    parent: Option<Entity>,
+    translation: glam::Vec3,
+    rotation: glam::Quat,
+    scale: glam::Vec3,
+}
+
+impl TrackedPlacement {
+    // Attached entities are placed by their LocalTransform rather than their own Transform
+    #[inline]
+    fn of(transform: Option<&Transform>, local: Option<&LocalTransform>) -> Option<Self> {
+        match (local, transform) {
+            (Some(local), _) => Some(Self {
+                parent: Some(local.parent),
+                translation: local.transform.translation,
+                rotation: local.transform.rotation,
+                scale: local.transform.scale,
+            }),
+            (None, Some(transform)) => Some(Self {
+                parent: None,
+                translation: transform.translation,
+                rotation: transform.rotation,
+                scale: transform.scale,
+            }),
+            (None, None) => None,
+        }
+    }
+}
+
+//--------------------------------------------------
+
+// Put on a static or kinematic to change how characters move while standing on it.
+// Friction scales ground damping and speed scales ground acceleration. Bounce is
+// how much of the landing speed is kept, and conveyor is the velocity characters
+// are dragged towards while on it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PhysicsMaterial {
+    pub friction: f32,
+    pub speed: f32,
+    pub bounce: f32,
+    pub conveyor: glam::Vec3,
+}
+
+impl Default for PhysicsMaterial {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            friction: 1.,
+            speed: 1.,
+            bounce: 0.,
+            conveyor: glam::Vec3::ZERO,
+        }
+    }
+}
+
+// Presets for game code to put on statics
+#[allow(dead_code)]
+impl PhysicsMaterial {
+    pub const ICE: Self = Self {
+        friction: 0.08,
+        speed: 0.15,
+        bounce: 0.,
+        conveyor: glam::Vec3::ZERO,
+    };
+
+    pub const MUD: Self = Self {
+        friction: 2.5,
+        speed: 0.4,
+        bounce: 0.,
+        conveyor: glam::Vec3::ZERO,
+    };
+
+    pub const BOUNCY: Self = Self {
+        friction: 1.,
+        speed: 1.,
+        bounce: 0.9,
+        conveyor: glam::Vec3::ZERO,
+    };
+
+    #[inline]
+    pub fn conveyor(velocity: glam::Vec3) -> Self {
+        Self {
+            conveyor: velocity,
+            ..Default::default()
+        }
+    }
+}
+
+// The material of whatever a character was last standing on. Kept up to date
+// alongside Grounded since the hits it comes from are cleared each step.
+struct GroundSurface(PhysicsMaterial);
+
 //====================================================================
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -139,6 +506,31 @@ impl CollisionDirection {
         }
     }
 
+    // Direction for a normal pointing away from the surface that was hit
+    #[inline]
+    fn from_normal(normal: glam::Vec3) -> Self {
+        let abs = normal.abs();
+
+        match (abs.x >= abs.y && abs.x >= abs.z, abs.y >= abs.z) {
+            (true, _) => Self::from_axis(Axis::X, &normal),
+            (false, true) => Self::from_axis(Axis::Y, &normal),
+            (false, false) => Self::from_axis(Axis::Z, &normal),
+        }
+    }
+
+    // Normal pointing out of the surface that was hit
+    #[inline]
+    pub fn normal(&self) -> glam::Vec3 {
+        match self {
+            CollisionDirection::PosX => glam::Vec3::X,
+            CollisionDirection::NegX => glam::Vec3::NEG_X,
+            CollisionDirection::PosY => glam::Vec3::Y,
+            CollisionDirection::NegY => glam::Vec3::NEG_Y,
+            CollisionDirection::PosZ => glam::Vec3::Z,
+            CollisionDirection::NegZ => glam::Vec3::NEG_Z,
+        }
+    }
+
     #[inline]
     fn flip(self) -> Self {
         match self {
@@ -152,6 +544,41 @@ impl CollisionDirection {
     }
 }
 
+#[derive(Debug, Clone, Copy, Default)]
+struct ClimbLimits {
+    step_height: Option<f32>,
+    max_slope: Option<f32>,
+}
+
+// Static and kinematic colliders as seen by a single dynamic entity, skipping any on layers it ignores.
+// Characters are also blocked by the rigid bodies prepped for the current step.
+struct StaticQuery<'a> {
+    world: &'a World,
+    grid: &'a SpatialGrid,
+    bodies: &'a [(Entity, Aabb)],
+    layers: CollisionLayers,
+}
+
+impl StaticQuery<'_> {
+    fn query(&self, bounds: &Aabb) -> Vec<Entity> {
+        let bodies = self
+            .bodies
+            .iter()
+            .filter(|(_, body_bounds)| body_bounds.intersects(bounds))
+            .map(|(body, _)| *body);
+
+        self.grid
+            .query(bounds)
+            .into_iter()
+            .chain(bodies)
+            .filter(|static_entity| {
+                self.layers
+                    .interacts_with(&CollisionLayers::of(self.world, *static_entity))
+            })
+            .collect()
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum Axis {
     X,
@@ -172,366 +599,2818 @@ impl Axis {
 
 //====================================================================
 
-#[derive(Default)]
-pub struct PhysicsHandler {
-    static_tracker: ChangeTracker<StaticCollisionType>,
-}
+const AXIS_CONTACT_STEPS: u32 = 8;
 
-impl PhysicsHandler {
-    pub fn tick_physics(&mut self, state: &mut State) {
-        prep_new_static(state, &mut self.static_tracker);
-        clear_collision_hits(state);
+const SLOPE_LIFT_LEEWAY: f32 = 1.5;
+const SLOPE_LIFT_EPSILON: f32 = 0.01;
+const STEP_WALL_NORMAL_Y: f32 = 0.3;
 
-        apply_character_movement(state);
-        apply_deacceleration(state);
-        apply_gravity(state);
+const DEPENETRATION_ITERATIONS: u32 = 4;
 
-        apply_velocity_collisions(state);
-        update_grounded(state);
+const SWEEP_ITERATIONS: u32 = 3;
+const SWEEP_SKIN: f32 = 0.01;
 
-        clear_controller_actions(state);
-    }
-}
+// How far below a grounded entity to look for the surface it's standing on
+const GROUND_PROBE_DEPTH: f32 = 1.;
 
-//====================================================================
+// Landings slower than this settle on bouncy surfaces instead of bouncing forever
+const MIN_BOUNCE_SPEED: f32 = 40.;
 
-fn prep_new_static(state: &mut State, tracker: &mut ChangeTracker<StaticCollisionType>) {
-    let new_static = tracker
-        .track(state.world_mut())
-        .added()
-        .into_iter()
-        .map(|(e, _)| e)
-        .collect::<Vec<_>>();
+// Parent chains longer than this are assumed to loop back on themselves
+const MAX_PARENT_DEPTH: usize = 16;
 
-    new_static.into_iter().for_each(|entity| {
-        let prepped = {
-            let entity = state.world().entity(entity).unwrap();
-            let transform = entity.get::<&Transform>().unwrap();
-            let shape = entity.get::<&CollisionShape>().unwrap();
+const DEFAULT_TICK_RATE: f32 = 60.;
+const DEFAULT_MAX_SUBSTEPS: u32 = 5;
 
-            PreppedCollisionShape::from_collision_shape(
-                &shape,
-                transform.translation,
-                transform.scale,
-            )
-        };
+pub struct PhysicsHandler {
+    static_tracker: ChangeTracker<StaticCollisionType>,
+    kinematic_tracker: ChangeTracker<KinematicCollisionType>,
+    shape_tracker: ChangeTracker<CollisionShape>,
+    static_grid: SpatialGrid,
 
-        state.world_mut().insert_one(entity, prepped).unwrap();
-    });
-}
+    trigger_overlaps: HashSet<(Entity, Entity)>,
+    trigger_events: Vec<TriggerEvent>,
 
-#[inline]
-fn clear_collision_hits(state: &mut State) {
-    state
-        .world_mut()
-        .query_mut::<&mut CollisionHits>()
-        .into_iter()
-        .for_each(|(_, hits)| hits.hits.clear());
+    touching: HashMap<(Entity, Entity), (CollisionDirection, glam::Vec3)>,
+    collision_events: Vec<CollisionEvent>,
+
+    timestep: f32,
+    max_substeps: u32,
+    accumulator: f32,
 }
 
-fn apply_character_movement(state: &mut State) {
-    let delta_time = state.time().delta_seconds();
+impl Default for PhysicsHandler {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_TICK_RATE, DEFAULT_MAX_SUBSTEPS)
+    }
+}
 
-    state
-        .world_mut()
-        .query_mut::<(
-            &CharacterController,
-            &Accel,
-            Option<&JumpImpulse>,
-            &mut Velocity,
-            Option<&Grounded>,
-        )>()
-        .into_iter()
-        .for_each(|(_, (controller, accel, jump, velocity, grounded))| {
-            controller
-                .movement_action_queue
-                .iter()
-                .for_each(|action| match action {
-                    MovementAction::Move((x_dir, z_dir)) => {
-                        velocity.0.x += x_dir * accel.0 * delta_time;
-                        velocity.0.z += z_dir * accel.0 * delta_time;
-                    }
+impl PhysicsHandler {
+    pub fn new(tick_rate: f32, max_substeps: u32) -> Self {
+        Self {
+            static_tracker: ChangeTracker::default(),
+            kinematic_tracker: ChangeTracker::default(),
+            shape_tracker: ChangeTracker::default(),
+            static_grid: SpatialGrid::default(),
 
-                    MovementAction::Jump => match (jump, grounded) {
-                        (Some(jump), Some(_)) => velocity.0.y = jump.0,
-                        _ => {}
-                    },
-                });
-        });
-}
+            trigger_overlaps: HashSet::default(),
+            trigger_events: Vec::new(),
 
-#[inline]
-fn apply_deacceleration(state: &mut State) {
-    state
-        .world_mut()
-        .query_mut::<(&mut Velocity, &DeaccelDampingFactor)>()
-        .into_iter()
-        .for_each(|(_, (velocity, damping))| {
-            velocity.0.x *= damping.0;
-            velocity.0.z *= damping.0;
-        });
-}
+            touching: HashMap::default(),
+            collision_events: Vec::new(),
 
-#[inline]
-fn apply_gravity(state: &mut State) {
-    let delta_time = state.time().delta_seconds();
+            timestep: 1. / tick_rate,
+            max_substeps,
+            accumulator: 0.,
+        }
+    }
 
-    state
-        .world_mut()
-        .query_mut::<(&Gravity, &mut Velocity)>()
-        .without::<&Grounded>()
-        .into_iter()
-        .for_each(|(_, (gravity, velocity))| velocity.0 += gravity.0 * delta_time);
-}
+    pub fn tick_physics(&mut self, state: &mut State) {
+        let delta_seconds = state.time().delta_seconds();
+        self.tick(state.world_mut(), delta_seconds);
+    }
 
-fn apply_velocity_collisions(state: &mut State) {
-    let delta_time = state.time().delta_seconds();
+    pub(crate) fn tick(&mut self, world: &mut World, delta_seconds: f32) {
+        self.trigger_events.clear();
+        self.collision_events.clear();
 
-    state
-        .world()
-        .query::<(
-            &mut Transform,
-            &mut Velocity,
-            &CollisionShape,
-            Option<&mut CollisionHits>,
-        )>()
-        .with::<&DynamicCollisionType>()
-        .without::<(&StaticCollisionType, &TriggerCollisionType)>()
-        .into_iter()
-        .for_each(|(dynamic_entity, (transform, velocity, shape, mut hits))| {
-            if velocity.0 == glam::Vec3::ZERO {
-                return;
-            }
+        restore_physics_transforms(world);
+        sync_static_colliders(
+            world,
+            &mut self.static_tracker,
+            &mut self.kinematic_tracker,
+            &mut self.shape_tracker,
+            &mut self.static_grid,
+        );
 
-            let movement = velocity.0 * delta_time;
+        // Drop any time we can't catch up on instead of spiralling after a hitch
+        self.accumulator =
+            (self.accumulator + delta_seconds).min(self.timestep * self.max_substeps as f32);
 
-            transform.translation += movement;
-            let prepped = PreppedCollisionShape::from_collision_shape(
-                shape,
-                transform.translation,
-                transform.scale,
-            );
+        keep_latest_move(world);
 
-            let static_hits = state
-                .world()
-                .query::<&PreppedCollisionShape>()
-                .with::<&StaticCollisionType>()
-                .without::<(&DynamicCollisionType, &TriggerCollisionType)>()
-                .into_iter()
-                .filter_map(|(entity, static_collision)| {
-                    match prepped.check_collision(static_collision) {
-                        true => Some(entity),
-                        false => None,
-                    }
-                })
-                .collect::<Vec<_>>();
+        let mut steps = 0;
 
-            if static_hits.is_empty() {
-                return;
-            }
+        while self.accumulator >= self.timestep {
+            self.accumulator -= self.timestep;
+            self.step(world);
 
-            transform.translation -= movement;
+            // Held movement applies every step but jumps etc. should only happen once
+            consume_one_shot_actions(world);
+            steps += 1;
+        }
 
-            do_axis_collision(
-                state.world(),
-                delta_time,
-                dynamic_entity,
-                transform,
-                velocity,
-                shape,
-                &mut hits,
-                Axis::X,
-                &static_hits,
-            );
+        if steps > 0 {
+            clear_controller_actions(world);
+        }
 
-            do_axis_collision(
-                state.world(),
-                delta_time,
-                dynamic_entity,
-                transform,
-                velocity,
-                shape,
-                &mut hits,
-                Axis::Z,
-                &static_hits,
-            );
+        interpolate_physics_transforms(world, self.accumulator / self.timestep);
+    }
 
-            do_axis_collision(
-                state.world(),
-                delta_time,
-                dynamic_entity,
-                transform,
-                velocity,
-                shape,
-                &mut hits,
-                Axis::Y,
-                &static_hits,
-            );
-        });
+    fn step(&mut self, world: &mut World) {
+        let delta_time = self.timestep;
+
+        store_previous_transforms(world);
+
+        // Riders come from the last step's hits so this goes before they're cleared
+        apply_kinematic_motion(world, &self.static_grid);
+        depenetrate_dynamics(world, &self.static_grid);
+        clear_collision_hits(world);
+
+        let bodies = prep_rigid_body_blockers(world);
+
+        apply_crouching(world, &self.static_grid, &bodies);
+        apply_character_movement(world, delta_time);
+        apply_damping(world, delta_time);
+        apply_gravity(world, delta_time);
+
+        apply_velocity_collisions(world, &self.static_grid, &bodies, delta_time);
+        resolve_dynamic_collisions(world, &self.static_grid);
+        rigid_body::step_rigid_bodies(world, &self.static_grid, delta_time);
+        apply_surface_bounce(world);
+        update_grounded(world);
+        update_ground_surfaces(world);
+
+        let first_trigger_event = self.trigger_events.len();
+
+        triggers::update_triggers(world, &mut self.trigger_overlaps, &mut self.trigger_events);
+        force_volumes::apply_force_volumes(
+            world,
+            &self.trigger_overlaps,
+            &self.trigger_events[first_trigger_event..],
+            delta_time,
+        );
+        collision_events::update_collision_events(
+            world,
+            &mut self.touching,
+            &mut self.collision_events,
+        );
+    }
 }
 
-fn do_axis_collision(
-    world: &World,
-    delta_time: f32,
-    dynamic_entity: Entity,
-    transform: &mut Transform,
-    velocity: &mut Velocity,
-    shape: &CollisionShape,
-    mut dynamic_hits: &mut Option<&mut CollisionHits>,
-    axis: Axis,
+// Settings, events and queries for game code, not all of which it uses yet
+#[allow(dead_code)]
+impl PhysicsHandler {
+    #[inline]
+    pub fn set_tick_rate(&mut self, tick_rate: f32) {
+        self.timestep = 1. / tick_rate;
+    }
 
-    check_against: &Vec<Entity>,
-) {
-    let movement = velocity.0 * axis.get_scale() * delta_time;
-    let direction = CollisionDirection::from_axis(axis, &movement);
+    #[inline]
+    pub fn set_max_substeps(&mut self, max_substeps: u32) {
+        self.max_substeps = max_substeps;
+    }
 
-    if movement == glam::Vec3::ZERO {
-        return;
+    // Events from the most recent tick - anything not drained is dropped next tick
+    #[inline]
+    pub fn drain_trigger_events(&mut self) -> impl Iterator<Item = TriggerEvent> + '_ {
+        self.trigger_events.drain(..)
     }
 
-    transform.translation += movement;
+    // Same as trigger events, only kept until the next tick
+    #[inline]
+    pub fn drain_collision_events(&mut self) -> impl Iterator<Item = CollisionEvent> + '_ {
+        self.collision_events.drain(..)
+    }
 
-    let prepped =
-        PreppedCollisionShape::from_collision_shape(shape, transform.translation, transform.scale);
+    // Outlines of every collider for debug drawing
+    #[inline]
+    pub fn debug_lines(&self, state: &State) -> Vec<DebugLine> {
+        debug::collect_debug_lines(state.world(), &self.trigger_overlaps)
+    }
 
-    let static_entities_hit = check_against
-        .into_iter()
-        .filter_map(|static_entity_id| {
-            let static_entity = world.entity(*static_entity_id).unwrap();
-            let static_collision = static_entity.get::<&PreppedCollisionShape>().unwrap();
+    // Static colliders are as of the last tick_physics so anything spawned since won't be hit yet
+    #[inline]
+    pub fn raycast(
+        &self,
+        state: &State,
+        origin: glam::Vec3,
+        direction: glam::Vec3,
+        max_distance: f32,
+        filter: &QueryFilter,
+    ) -> Option<QueryHit> {
+        queries::raycast(
+            state.world(),
+            &self.static_grid,
+            origin,
+            direction,
+            max_distance,
+            filter,
+        )
+    }
 
-            match prepped.check_collision(&static_collision) {
-                true => {
-                    if let Some(mut hits) = static_entity.get::<&mut CollisionHits>() {
-                        hits.hits.push((dynamic_entity, direction));
-                    }
-                    Some(*static_entity_id)
+    #[inline]
+    pub fn shape_cast(
+        &self,
+        state: &State,
+        shape: &CollisionShape,
+        from: glam::Vec3,
+        to: glam::Vec3,
+        filter: &QueryFilter,
+    ) -> Option<QueryHit> {
+        queries::shape_cast(state.world(), &self.static_grid, shape, from, to, filter)
+    }
+
+    #[inline]
+    pub fn overlap(
+        &self,
+        state: &State,
+        shape: &CollisionShape,
+        translation: glam::Vec3,
+        filter: &QueryFilter,
+    ) -> Vec<Entity> {
+        queries::overlap(state.world(), &self.static_grid, shape, translation, filter)
+    }
+}
+
+//====================================================================
+
+// Kinematics are kept prepped and in the grid alongside statics
+fn sync_static_colliders(
+    world: &mut World,
+    static_tracker: &mut ChangeTracker<StaticCollisionType>,
+    kinematic_tracker: &mut ChangeTracker<KinematicCollisionType>,
+    shape_tracker: &mut ChangeTracker<CollisionShape>,
+    grid: &mut SpatialGrid,
+) {
+    let (mut to_prep, mut to_remove) = {
+        let mut changes = static_tracker.track(world);
+
+        let added = changes.added().map(|(e, _)| e).collect::<Vec<_>>();
+        let removed = changes.removed().map(|(e, _)| e).collect::<Vec<_>>();
+
+        (added, removed)
+    };
+
+    {
+        let mut changes = kinematic_tracker.track(world);
+
+        to_prep.extend(changes.added().map(|(e, _)| e));
+        to_remove.extend(changes.removed().map(|(e, _)| e));
+    }
+
+    {
+        let mut changes = shape_tracker.track(world);
+
+        to_prep.extend(changes.added().map(|(e, _)| e));
+        to_prep.extend(changes.changed().map(|(e, _, _)| e));
+        to_remove.extend(changes.removed().map(|(e, _)| e));
+    }
+
+    // Only prepped colliders and what they're attached to are compared, not every Transform
+    let mut moved = HashSet::new();
+    let mut untracked = Vec::new();
+
+    world
+        .query_mut::<(
+            Option<&Transform>,
+            Option<&LocalTransform>,
+            &mut TrackedPlacement,
+        )>()
+        .into_iter()
+        .for_each(|(entity, (transform, local, tracked))| {
+            match TrackedPlacement::of(transform, local) {
+                Some(current) if current == *tracked => {}
+                Some(current) => {
+                    *tracked = current;
+                    moved.insert(entity);
+                }
+                None => {
+                    untracked.push(entity);
+                    moved.insert(entity);
                 }
-                false => None,
             }
-        })
-        .collect::<Vec<_>>();
+        });
 
-    if !static_entities_hit.is_empty() {
-        transform.translation -= movement;
-        let direction = direction.flip();
+    untracked.into_iter().for_each(|entity| {
+        world.remove_one::<TrackedPlacement>(entity).unwrap();
+    });
 
-        if let Some(hits) = &mut dynamic_hits {
-            static_entities_hit.into_iter().for_each(|hit| {
-                hits.hits.push((hit, direction));
-            });
+    to_prep.extend(moved.iter().copied().filter(|entity| {
+        world
+            .entity(*entity)
+            .map(|entity| {
+                entity.has::<StaticCollisionType>() || entity.has::<KinematicCollisionType>()
+            })
+            .unwrap_or(false)
+    }));
+
+    // Children need re-prepping whenever anything they're attached to moves too
+    if !moved.is_empty() {
+        world
+            .query::<&LocalTransform>()
+            .with::<&PreppedCollisionShape>()
+            .into_iter()
+            .filter(|(_, local)| has_moved_parent(world, local.parent, &moved))
+            .for_each(|(entity, _)| to_prep.push(entity));
+    }
+
+    // Despawned entities aren't reported by the change trackers
+    to_remove.extend(grid.entities().filter(|entity| !world.contains(*entity)));
+
+    to_remove.into_iter().for_each(|entity| {
+        grid.remove(entity);
+
+        if world.contains(entity) {
+            world.remove_one::<PreppedCollisionShape>(entity).ok();
+            world.remove_one::<TrackedPlacement>(entity).ok();
         }
+    });
+
+    to_prep.sort_unstable();
+    to_prep.dedup();
+
+    to_prep.into_iter().for_each(|entity| {
+        let prepped = {
+            let entity = match world.entity(entity) {
+                Ok(entity) => entity,
+                Err(_) => return,
+            };
+
+            let (transform, shape) = match (
+                entity.has::<StaticCollisionType>() || entity.has::<KinematicCollisionType>(),
+                world_transform(world, entity.entity()),
+                entity.get::<&CollisionShape>(),
+            ) {
+                (true, Some(transform), Some(shape)) => (transform, shape),
+                _ => return,
+            };
+
+            PreppedCollisionShape::from_collision_shape(
+                &shape,
+                transform.translation,
+                transform.rotation,
+                transform.scale,
+            )
+        };
+
+        grid.insert(entity, prepped.bounds());
+        world.insert_one(entity, prepped).unwrap();
+
+        track_placement(world, entity);
+    });
+}
+
+// Start watching for moves of a prepped collider and of everything up its parent chain
+fn track_placement(world: &mut World, entity: Entity) {
+    let mut current = entity;
+
+    for _ in 0..=MAX_PARENT_DEPTH {
+        let placement = match world.entity(current) {
+            Ok(entity) => TrackedPlacement::of(
+                entity.get::<&Transform>().as_deref(),
+                entity.get::<&LocalTransform>().as_deref(),
+            ),
+            Err(_) => None,
+        };
+
+        let placement = match placement {
+            Some(placement) => placement,
+            None => return,
+        };
+
+        world.insert_one(current, placement).unwrap();
+
+        current = match placement.parent {
+            Some(parent) => parent,
+            None => return,
+        };
     }
 }
 
-fn update_grounded(state: &mut State) {
-    let remove_grounded = state
-        .world_mut()
-        .query_mut::<&CollisionHits>()
-        .with::<(&Gravity, &Grounded)>()
+fn apply_kinematic_motion(world: &mut World, grid: &SpatialGrid) {
+    // Attached kinematics go by where they are in the world so they carry riders along too
+    let kinematics = world
+        .query::<()>()
+        .with::<&KinematicCollisionType>()
         .into_iter()
-        .filter_map(|(entity, hits)| {
-            match hits
-                .hits
-                .iter()
-                .any(|(_, collision)| *collision == CollisionDirection::PosY)
-            {
-                true => None,
-                false => Some(entity),
-            }
+        .filter_map(|(entity, _)| {
+            world_transform(world, entity)
+                .map(|transform| (entity, KinematicMotion::from_transform(&transform)))
         })
         .collect::<Vec<_>>();
 
-    let add_grounded = state
-        .world_mut()
-        .query_mut::<&CollisionHits>()
-        .with::<&Gravity>()
-        .without::<&Grounded>()
+    let mut new_kinematics = Vec::new();
+    let mut moved = Vec::new();
+
+    kinematics.into_iter().for_each(|(entity, current)| {
+        match world.get::<&mut KinematicMotion>(entity) {
+            Ok(mut motion) => {
+                let previous = std::mem::replace(&mut *motion, current);
+
+                if previous.translation != current.translation
+                    || previous.rotation != current.rotation
+                {
+                    moved.push((entity, previous, current));
+                }
+            }
+
+            Err(_) => new_kinematics.push((entity, current)),
+        }
+    });
+
+    new_kinematics.into_iter().for_each(|(entity, motion)| {
+        world.insert_one(entity, motion).unwrap();
+    });
+
+    if moved.is_empty() {
+        return;
+    }
+
+    // Carry anything that was standing on a kinematic as of the last step
+    let riders = world
+        .query::<&CollisionHits>()
+        .with::<&DynamicCollisionType>()
+        .without::<&StaticCollisionType>()
+        .without::<&TriggerCollisionType>()
         .into_iter()
         .filter_map(|(entity, hits)| {
-            match hits
-                .hits
-                .iter()
-                .any(|(_, collision)| *collision == CollisionDirection::PosY)
-            {
-                true => Some(entity),
-                false => None,
-            }
+            hits.hits.iter().find_map(|(other, direction)| {
+                match *direction == CollisionDirection::PosY {
+                    true => moved
+                        .iter()
+                        .position(|(kinematic, _, _)| kinematic == other)
+                        .map(|index| (entity, index)),
+                    false => None,
+                }
+            })
         })
         .collect::<Vec<_>>();
 
-    remove_grounded.into_iter().for_each(|entity| {
-        state.world_mut().remove_one::<Grounded>(entity).unwrap();
+    riders.into_iter().for_each(|(rider, index)| {
+        let (_, previous, current) = &moved[index];
+        let position = world.get::<&Transform>(rider).unwrap().translation;
+
+        let rotation = current.rotation * previous.rotation.inverse();
+        let carried = current.translation + rotation * (position - previous.translation);
+
+        let offset = push_dynamic(world, grid, rider, carried - position);
+        shift_interpolation(world, rider, offset);
     });
 
-    add_grounded.into_iter().for_each(|entity| {
-        state.world_mut().insert_one(entity, Grounded).unwrap();
+    // Then shove anything the kinematic has moved into back out of it
+    moved.iter().for_each(|(kinematic, _, _)| {
+        let kinematic_shape = match world.get::<&PreppedCollisionShape>(*kinematic) {
+            Ok(shape) => shape,
+            Err(_) => return,
+        };
+
+        let kinematic_bounds = kinematic_shape.bounds();
+        let kinematic_layers = CollisionLayers::of(world, *kinematic);
+
+        let overlapping = world
+            .query::<(&Transform, &CollisionShape)>()
+            .with::<&DynamicCollisionType>()
+            .without::<&StaticCollisionType>()
+            .without::<&TriggerCollisionType>()
+            .into_iter()
+            .filter_map(|(entity, (transform, shape))| {
+                let prepped = prep_dynamic_shape(shape, transform.translation, transform);
+
+                match kinematic_bounds.intersects(&prepped.bounds())
+                    && kinematic_layers.interacts_with(&CollisionLayers::of(world, entity))
+                {
+                    true => prepped
+                        .contact(&kinematic_shape)
+                        .map(|contact| (entity, contact)),
+                    false => None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        overlapping.into_iter().for_each(|(entity, contact)| {
+            let offset = push_dynamic(
+                world,
+                grid,
+                entity,
+                contact.normal * (contact.depth + SWEEP_SKIN),
+            );
+            shift_interpolation(world, entity, offset);
+        });
     });
 }
 
+// Anything spawned, teleported or grown into a static would otherwise have every move
+// reverted and be stuck there. Push it out the shortest way from its deepest overlap,
+// a few times over in case that shoves it into something else.
+fn depenetrate_dynamics(world: &mut World, grid: &SpatialGrid) {
+    world
+        .query::<(
+            &mut Transform,
+            &CollisionShape,
+            Option<&mut Velocity>,
+            Option<&CollisionLayers>,
+        )>()
+        .with::<&DynamicCollisionType>()
+        .without::<&StaticCollisionType>()
+        .without::<&TriggerCollisionType>()
+        .without::<&RigidBody>()
+        .into_iter()
+        .for_each(|(entity, (transform, shape, mut velocity, layers))| {
+            let statics = StaticQuery {
+                world,
+                grid,
+                bodies: &[],
+                layers: layers.copied().unwrap_or_default(),
+            };
+
+            let mut offset = glam::Vec3::ZERO;
+
+            for _ in 0..DEPENETRATION_ITERATIONS {
+                let contact = match penetration(world, &statics, shape, transform) {
+                    Some(contact) => contact,
+                    None => break,
+                };
+
+                let push = contact.normal * (contact.depth + SWEEP_SKIN);
+                transform.translation += push;
+                offset += push;
+
+                // Don't keep heading back into it
+                if let Some(velocity) = &mut velocity {
+                    let into = velocity.0.dot(contact.normal);
+
+                    if into < 0. {
+                        velocity.0 -= contact.normal * into;
+                    }
+                }
+            }
+
+            shift_interpolation(world, entity, offset);
+        });
+}
+
 #[inline]
-fn clear_controller_actions(state: &mut State) {
-    state
-        .world_mut()
-        .query_mut::<&mut CharacterController>()
+fn overlaps_statics(world: &World, statics: &StaticQuery, prepped: &PreppedCollisionShape) -> bool {
+    statics
+        .query(&prepped.bounds())
         .into_iter()
-        .for_each(|(_, controller)| controller.movement_action_queue.clear());
+        .any(|static_entity| {
+            let static_collision = world.get::<&PreppedCollisionShape>(static_entity).unwrap();
+            prepped.check_collision(&static_collision)
+        })
 }
 
-//====================================================================
+// Deepest overlap between a dynamic entity and the statics around it, if any
+fn penetration(
+    world: &World,
+    statics: &StaticQuery,
+    shape: &CollisionShape,
+    transform: &Transform,
+) -> Option<Contact> {
+    let prepped = prep_dynamic_shape(shape, transform.translation, transform);
 
-enum PreppedCollisionShape {
-    Box((Range, Range, Range)),
+    let overlapping = statics
+        .query(&prepped.bounds())
+        .into_iter()
+        .filter(|static_entity| {
+            let static_collision = world.get::<&PreppedCollisionShape>(*static_entity).unwrap();
+            prepped.check_collision(&static_collision)
+        })
+        .collect::<Vec<_>>();
+
+    deepest_contact(world, &prepped, &overlapping).filter(|contact| contact.depth > 0.)
 }
 
-struct Range {
-    min: f32,
-    max: f32,
+#[inline]
+fn clear_collision_hits(world: &mut World) {
+    world
+        .query_mut::<&mut CollisionHits>()
+        .into_iter()
+        .for_each(|(_, hits)| hits.hits.clear());
 }
 
-impl PreppedCollisionShape {
-    fn from_collision_shape(
-        value: &CollisionShape,
-        translation: glam::Vec3,
-        scale: glam::Vec3,
-    ) -> Self {
-        match value {
-            CollisionShape::Box {
-                half_width,
-                half_height,
-                half_depth,
-            } => PreppedCollisionShape::Box((
-                Range {
-                    min: translation.x - half_width * scale.x,
-                    max: translation.x + half_width * scale.x,
-                },
-                Range {
-                    min: translation.y - half_height * scale.y,
-                    max: translation.y + half_height * scale.y,
-                },
-                Range {
-                    min: translation.z - half_depth * scale.z,
-                    max: translation.z + half_depth * scale.z,
+fn apply_crouching(world: &mut World, grid: &SpatialGrid, bodies: &[(Entity, Aabb)]) {
+    let changes = world
+        .query::<(
+            &CharacterController,
+            &CrouchControl,
+            &mut Transform,
+            &mut CollisionShape,
+            Option<&mut Crouched>,
+            Option<&CollisionLayers>,
+        )>()
+        .into_iter()
+        .filter_map(
+            |(entity, (controller, crouch, transform, shape, crouched, layers))| {
+                // Last press or release this step wins
+                let wants_crouch =
+                    controller
+                        .movement_action_queue
+                        .iter()
+                        .fold(None, |wants_crouch, action| match action {
+                            MovementAction::Crouch => Some(true),
+                            MovementAction::CrouchRelease => Some(false),
+                            _ => wants_crouch,
+                        });
+
+                match (crouched, wants_crouch) {
+                    (None, Some(true)) => {
+                        let (crouched_shape, drop) =
+                            crouched_shape(shape, crouch.height, transform.scale)?;
+
+                        let standing = std::mem::replace(shape, crouched_shape);
+                        transform.translation.y -= drop;
+
+                        Some((
+                            entity,
+                            Some(Crouched {
+                                standing,
+                                drop,
+                                speed: crouch.speed,
+                                standing_up: false,
+                            }),
+                        ))
+                    }
+
+                    (Some(crouched), wants_crouch) => {
+                        if let Some(wants_crouch) = wants_crouch {
+                            crouched.standing_up = !wants_crouch;
+                        }
+
+                        if !crouched.standing_up {
+                            return None;
+                        }
+
+                        // Keep ducking under anything that's in the way of standing up
+                        let statics = StaticQuery {
+                            world,
+                            grid,
+                            bodies,
+                            layers: layers.copied().unwrap_or_default(),
+                        };
+
+                        let standing_at = transform.translation + glam::Vec3::Y * crouched.drop;
+                        let standing =
+                            prep_dynamic_shape(&crouched.standing, standing_at, transform);
+
+                        if overlaps_statics(world, &statics, &standing) {
+                            return None;
+                        }
+
+                        *shape = crouched.standing.clone();
+                        transform.translation = standing_at;
+
+                        Some((entity, None))
+                    }
+
+                    (None, _) => None,
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    changes
+        .into_iter()
+        .for_each(|(entity, crouched)| match crouched {
+            Some(crouched) => world.insert_one(entity, crouched).unwrap(),
+            None => {
+                world.remove_one::<Crouched>(entity).unwrap();
+            }
+        });
+}
+
+// Shorter version of a shape and how far its centre drops to keep the bottom in place
+fn crouched_shape(
+    shape: &CollisionShape,
+    height: f32,
+    scale: glam::Vec3,
+) -> Option<(CollisionShape, f32)> {
+    let (crouched, half_height, crouched_half_height) = match shape {
+        CollisionShape::Box {
+            half_width,
+            half_height,
+            half_depth,
+        } => {
+            let crouched_half_height = half_height * height;
+
+            (
+                CollisionShape::Box {
+                    half_width: *half_width,
+                    half_height: crouched_half_height,
+                    half_depth: *half_depth,
                 },
-            )),
+                *half_height,
+                crouched_half_height,
+            )
         }
-    }
 
-    fn check_collision(&self, other: &PreppedCollisionShape) -> bool {
-        match (self, other) {
-            (PreppedCollisionShape::Box(a), PreppedCollisionShape::Box(b)) => aabb_vs_aabb(a, b),
+        // Can't get any shorter than its own caps
+        CollisionShape::Capsule {
+            radius,
+            half_height,
+        } => {
+            let crouched_half_height = (half_height * height).max(*radius).min(*half_height);
+
+            (
+                CollisionShape::Capsule {
+                    radius: *radius,
+                    half_height: crouched_half_height,
+                },
+                *half_height,
+                crouched_half_height,
+            )
         }
+
+        _ => return None,
+    };
+
+    Some((crouched, (half_height - crouched_half_height) * scale.y))
+}
+
+fn apply_character_movement(world: &mut World, delta_time: f32) {
+    let new_controllers = world
+        .query_mut::<()>()
+        .with::<&CharacterController>()
+        .without::<&JumpTimers>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    new_controllers.into_iter().for_each(|entity| {
+        let timers = JumpTimers {
+            since_grounded: f32::INFINITY,
+            buffered: 0.,
+            air_jumps_used: 0,
+            jumping: false,
+        };
+        world.insert_one(entity, timers).unwrap();
+    });
+
+    world
+        .query_mut::<(
+            &CharacterController,
+            &Accel,
+            &mut Velocity,
+            &mut JumpTimers,
+            Option<&Grounded>,
+            Option<&AirControl>,
+            Option<&Damping>,
+            Option<&JumpImpulse>,
+            Option<&JumpCutFactor>,
+            Option<&AirJumps>,
+            Option<&CoyoteTime>,
+            Option<&JumpBuffer>,
+            Option<&GroundSurface>,
+            Option<&Crouched>,
+        )>()
+        .into_iter()
+        .for_each(
+            |(
+                _,
+                (
+                    controller,
+                    accel,
+                    velocity,
+                    timers,
+                    grounded,
+                    air_control,
+                    damping,
+                    jump,
+                    jump_cut,
+                    air_jumps,
+                    coyote,
+                    buffer,
+                    surface,
+                    crouched,
+                ),
+            )| {
+                let accel = match (grounded, air_control, surface) {
+                    (None, Some(air_control), _) => air_control.accel,
+                    (Some(_), _, Some(surface)) => accel.0 * surface.0.speed,
+                    _ => accel.0,
+                } * crouched.map(|crouched| crouched.speed).unwrap_or(1.);
+
+                // Damping is applied after accelerating, so scale up the acceleration to
+                // match integrating both together exactly. Otherwise top speed would
+                // depend on the tick rate.
+                let damping = damping_rate(grounded.is_some(), damping, air_control, surface);
+                let accel_time = match damping > 0. {
+                    true => ((damping * delta_time).exp() - 1.) / damping,
+                    false => delta_time,
+                };
+
+                let mut jump_pressed = false;
+                let mut jump_released = false;
+
+                controller
+                    .movement_action_queue
+                    .iter()
+                    .for_each(|action| match action {
+                        MovementAction::Move((x_dir, z_dir)) => {
+                            velocity.0.x += x_dir * accel * accel_time;
+                            velocity.0.z += z_dir * accel * accel_time;
+                        }
+
+                        MovementAction::Jump => jump_pressed = true,
+                        MovementAction::JumpRelease => jump_released = true,
+
+                        MovementAction::Crouch | MovementAction::CrouchRelease => {}
+                    });
+
+                if grounded.is_some() {
+                    timers.since_grounded = 0.;
+                    timers.air_jumps_used = 0;
+                    timers.jumping = false;
+                } else {
+                    timers.since_grounded += delta_time;
+                }
+
+                timers.buffered = match jump_pressed {
+                    true => buffer.map(|buffer| buffer.0).unwrap_or(0.),
+                    false => timers.buffered - delta_time,
+                };
+
+                let can_jump = timers.since_grounded <= coyote.map(|coyote| coyote.0).unwrap_or(0.);
+
+                // Air jumps need a fresh press, a buffered one is saved for landing
+                let can_air_jump = jump_pressed
+                    && timers.air_jumps_used < air_jumps.map(|air_jumps| air_jumps.0).unwrap_or(0);
+
+                match (jump, can_jump, can_air_jump) {
+                    (Some(jump), true, _) if jump_pressed || timers.buffered > 0. => {
+                        velocity.0.y = jump.0;
+                        timers.jumping = true;
+
+                        // Don't let the same bit of ground or press be used for a second jump
+                        timers.since_grounded = f32::INFINITY;
+                        timers.buffered = 0.;
+                    }
+
+                    (Some(jump), false, true) => {
+                        velocity.0.y = jump.0;
+                        timers.jumping = true;
+
+                        timers.air_jumps_used += 1;
+                        timers.buffered = 0.;
+                    }
+
+                    _ => {}
+                }
+
+                // Only cut our own jumps short, not being launched upwards by something else
+                if let (true, true, Some(jump_cut)) = (jump_released, timers.jumping, jump_cut) {
+                    if velocity.0.y > 0. {
+                        velocity.0.y *= jump_cut.0;
+                    }
+                    timers.jumping = false;
+                }
+            },
+        );
+}
+
+fn apply_damping(world: &mut World, delta_time: f32) {
+    world
+        .query_mut::<(
+            &mut Velocity,
+            Option<&Damping>,
+            Option<&AirControl>,
+            Option<&MaxSpeed>,
+            Option<&Grounded>,
+            Option<&GroundSurface>,
+        )>()
+        .into_iter()
+        .for_each(
+            |(_, (velocity, damping, air_control, max_speed, grounded, surface))| {
+                let rate = damping_rate(grounded.is_some(), damping, air_control, surface);
+                let decay = (-rate * delta_time).exp();
+
+                // Decay towards the speed of the surface so conveyors drag things along
+                let conveyor = match grounded {
+                    Some(_) => surface
+                        .map(|surface| surface.0.conveyor)
+                        .unwrap_or_default(),
+                    None => glam::Vec3::ZERO,
+                };
+
+                velocity.0.x = conveyor.x + (velocity.0.x - conveyor.x) * decay;
+                velocity.0.z = conveyor.z + (velocity.0.z - conveyor.z) * decay;
+
+                if let Some(max_speed) = max_speed {
+                    let horizontal =
+                        glam::vec2(velocity.0.x, velocity.0.z).clamp_length_max(max_speed.0);
+
+                    velocity.0.x = horizontal.x;
+                    velocity.0.z = horizontal.y;
+                }
+            },
+        );
+}
+
+#[inline]
+fn damping_rate(
+    grounded: bool,
+    damping: Option<&Damping>,
+    air_control: Option<&AirControl>,
+    surface: Option<&GroundSurface>,
+) -> f32 {
+    let friction = surface.map(|surface| surface.0.friction).unwrap_or(1.);
+
+    match (grounded, air_control) {
+        (false, Some(air_control)) => air_control.damping,
+        (true, _) => damping.map(|damping| damping.0).unwrap_or(0.) * friction,
+        _ => damping.map(|damping| damping.0).unwrap_or(0.),
     }
 }
 
 #[inline]
-fn aabb_vs_aabb(a: &(Range, Range, Range), b: &(Range, Range, Range)) -> bool {
-    a.0.min <= b.0.max
-        && a.0.max >= b.0.min
-        && a.1.min <= b.1.max
-        && a.1.max >= b.1.min
-        && a.2.min <= b.2.max
-        && a.2.max >= b.2.min
+fn apply_gravity(world: &mut World, delta_time: f32) {
+    world
+        .query_mut::<(&Gravity, &mut Velocity)>()
+        .without::<&Grounded>()
+        .into_iter()
+        .for_each(|(_, (gravity, velocity))| velocity.0 += gravity.0 * delta_time);
 }
 
-//====================================================================
+// Rigid bodies block characters like statics do, so keep them prepped for the step
+fn prep_rigid_body_blockers(world: &mut World) -> Vec<(Entity, Aabb)> {
+    let prepped = world
+        .query_mut::<(&Transform, &CollisionShape)>()
+        .with::<(&RigidBody, &DynamicCollisionType)>()
+        .without::<&StaticCollisionType>()
+        .without::<&TriggerCollisionType>()
+        .into_iter()
+        .map(|(entity, (transform, shape))| (entity, prep_rigid_body_shape(shape, transform)))
+        .collect::<Vec<_>>();
+
+    prepped
+        .into_iter()
+        .map(|(entity, prepped)| {
+            let bounds = prepped.bounds();
+            world.insert_one(entity, prepped).unwrap();
+
+            (entity, bounds)
+        })
+        .collect()
+}
+
+fn apply_velocity_collisions(
+    world: &mut World,
+    grid: &SpatialGrid,
+    bodies: &[(Entity, Aabb)],
+    delta_time: f32,
+) {
+    world
+        .query::<(
+            &mut Transform,
+            &mut Velocity,
+            &CollisionShape,
+            Option<&mut CollisionHits>,
+            Option<&ContinuousCollision>,
+            Option<&MaxStepHeight>,
+            Option<&MaxSlopeAngle>,
+            Option<&Grounded>,
+            Option<&CollisionLayers>,
+        )>()
+        .with::<&DynamicCollisionType>()
+        .without::<&StaticCollisionType>()
+        .without::<&TriggerCollisionType>()
+        .without::<&RigidBody>()
+        .into_iter()
+        .for_each(
+            |(
+                dynamic_entity,
+                (transform, velocity, shape, mut hits, continuous, step, slope, grounded, layers),
+            )| {
+                if velocity.0 == glam::Vec3::ZERO {
+                    return;
+                }
+
+                let statics = StaticQuery {
+                    world,
+                    grid,
+                    bodies,
+                    layers: layers.copied().unwrap_or_default(),
+                };
+
+                if continuous.is_some() {
+                    apply_swept_movement(
+                        world,
+                        &statics,
+                        dynamic_entity,
+                        transform,
+                        velocity.0 * delta_time,
+                        shape,
+                        &mut hits,
+                    );
+                    return;
+                }
+
+                let limits = ClimbLimits {
+                    step_height: step.map(|step| step.0),
+                    max_slope: slope.map(|slope| slope.0),
+                };
+
+                let movement = velocity.0 * delta_time;
+
+                transform.translation += movement;
+                let prepped = prep_dynamic_shape(shape, transform.translation, transform);
+
+                let static_hits = statics
+                    .query(&prepped.bounds())
+                    .into_iter()
+                    .filter(|entity| {
+                        let static_collision =
+                            world.get::<&PreppedCollisionShape>(*entity).unwrap();
+
+                        prepped.check_collision(&static_collision)
+                    })
+                    .collect::<Vec<_>>();
+
+                if !static_hits.is_empty() {
+                    transform.translation -= movement;
+
+                    [Axis::X, Axis::Z, Axis::Y].into_iter().for_each(|axis| {
+                        do_axis_collision(
+                            world,
+                            &statics,
+                            delta_time,
+                            dynamic_entity,
+                            transform,
+                            velocity,
+                            shape,
+                            &mut hits,
+                            axis,
+                            &static_hits,
+                            limits,
+                        );
+                    });
+                }
+
+                if let (Some(step_height), Some(_), Some(hits)) =
+                    (limits.step_height, grounded, &mut hits)
+                {
+                    if velocity.0.y <= 0. {
+                        snap_to_ground(
+                            world,
+                            &statics,
+                            dynamic_entity,
+                            transform,
+                            shape,
+                            hits,
+                            step_height,
+                        );
+                    }
+                }
+            },
+        );
+}
+
+fn apply_swept_movement(
+    world: &World,
+    statics: &StaticQuery,
+    dynamic_entity: Entity,
+    transform: &mut Transform,
+    movement: glam::Vec3,
+    shape: &CollisionShape,
+    dynamic_hits: &mut Option<&mut CollisionHits>,
+) {
+    let mut remaining = movement;
+
+    for _ in 0..SWEEP_ITERATIONS {
+        if remaining == glam::Vec3::ZERO {
+            return;
+        }
+
+        let bounds = prep_dynamic_shape(shape, transform.translation, transform).bounds();
+        let swept_bounds = bounds.union(&Aabb::new(bounds.min + remaining, bounds.max + remaining));
+
+        // Statics are swept exactly, only the moving collider is treated as its AABB
+        let earliest = statics
+            .query(&swept_bounds)
+            .into_iter()
+            .filter_map(|static_entity| {
+                let static_collision = world.get::<&PreppedCollisionShape>(static_entity).unwrap();
+
+                static_collision
+                    .sweep(&bounds, remaining)
+                    .map(|(time, normal)| (static_entity, time, normal))
+            })
+            .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b));
+
+        let (static_entity, time, normal) = match earliest {
+            Some(hit) => hit,
+            None => {
+                transform.translation += remaining;
+                return;
+            }
+        };
+
+        // Stop just short of the surface so the next sweep starts outside of it
+        transform.translation += remaining * time + normal * SWEEP_SKIN;
+
+        let direction = CollisionDirection::from_normal(normal);
+
+        if let Ok(mut hits) = world.get::<&mut CollisionHits>(static_entity) {
+            hits.hits.push((dynamic_entity, direction.flip()));
+        }
+
+        if let Some(hits) = dynamic_hits {
+            hits.hits.push((static_entity, direction));
+        }
+
+        // Slide along the surface with whatever movement is left
+        remaining *= 1. - time;
+        remaining -= normal * remaining.dot(normal);
+    }
+}
+
+fn do_axis_collision(
+    world: &World,
+    statics: &StaticQuery,
+    delta_time: f32,
+    dynamic_entity: Entity,
+    transform: &mut Transform,
+    velocity: &mut Velocity,
+    shape: &CollisionShape,
+    mut dynamic_hits: &mut Option<&mut CollisionHits>,
+    axis: Axis,
+
+    check_against: &Vec<Entity>,
+    limits: ClimbLimits,
+) {
+    let movement = velocity.0 * axis.get_scale() * delta_time;
+    let direction = CollisionDirection::from_axis(axis, &movement);
+
+    if movement == glam::Vec3::ZERO {
+        return;
+    }
+
+    transform.translation += movement;
+
+    let prepped = prep_dynamic_shape(shape, transform.translation, transform);
+
+    let static_entities_hit = check_against
+        .into_iter()
+        .filter_map(|static_entity_id| {
+            let static_entity = world.entity(*static_entity_id).unwrap();
+            let static_collision = static_entity.get::<&PreppedCollisionShape>().unwrap();
+
+            match prepped.check_collision(&static_collision) {
+                true => {
+                    if let Some(mut hits) = static_entity.get::<&mut CollisionHits>() {
+                        hits.hits.push((dynamic_entity, direction));
+                    }
+                    Some(*static_entity_id)
+                }
+                false => None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    if !static_entities_hit.is_empty() {
+        let surface_normal =
+            deepest_contact(world, &prepped, &static_entities_hit).map(|contact| contact.normal);
+
+        transform.translation -= movement;
+
+        if axis != Axis::Y
+            && try_climb(
+                world,
+                statics,
+                transform,
+                shape,
+                movement,
+                surface_normal,
+                limits,
+            )
+        {
+            return;
+        }
+
+        // Slide up to the point of contact instead of stopping a full step short
+        let travel = find_axis_contact(world, transform, shape, movement, &static_entities_hit);
+        transform.translation += movement * travel;
+
+        let mut direction = direction.flip();
+
+        // Walking into ground shallow enough to stand on counts as standing on it, so
+        // grounding follows the surface normal rather than the axis that was moved along
+        if let (true, Some(normal)) = (axis != Axis::Y, surface_normal) {
+            let walkable = limits
+                .max_slope
+                .map(|max_slope| max_slope.cos())
+                .unwrap_or(std::f32::consts::FRAC_1_SQRT_2);
+
+            if normal.y >= walkable {
+                direction = CollisionDirection::PosY;
+            }
+        }
+
+        // Too steep to stand on, so slide down the slope instead of landing
+        if let (Axis::Y, Some(normal), Some(max_slope)) = (axis, surface_normal, limits.max_slope) {
+            if normal.y > 0. && normal.y < max_slope.cos() {
+                let remaining = movement * (1. - travel);
+                let slide = remaining - normal * remaining.dot(normal);
+
+                let candidates = nearby_statics(statics, transform, shape, slide);
+                let slide_travel = find_axis_contact(world, transform, shape, slide, &candidates);
+                transform.translation += slide * slide_travel;
+
+                direction = CollisionDirection::from_normal(glam::vec3(normal.x, 0., normal.z));
+            }
+        }
+
+        if let Some(hits) = &mut dynamic_hits {
+            static_entities_hit.into_iter().for_each(|hit| {
+                hits.hits.push((hit, direction));
+            });
+        }
+    }
+}
+
+// Walk up shallow slopes and step up onto ledges instead of stopping dead against them
+fn try_climb(
+    world: &World,
+    statics: &StaticQuery,
+    transform: &mut Transform,
+    shape: &CollisionShape,
+    movement: glam::Vec3,
+    surface_normal: Option<glam::Vec3>,
+    limits: ClimbLimits,
+) -> bool {
+    let normal = match surface_normal {
+        Some(normal) => normal,
+        None => return false,
+    };
+
+    let start = transform.translation;
+    let is_free = |translation: glam::Vec3| {
+        let prepped = prep_dynamic_shape(shape, translation, transform);
+
+        !statics
+            .query(&prepped.bounds())
+            .into_iter()
+            .any(|static_entity| {
+                let static_collision = world.get::<&PreppedCollisionShape>(static_entity).unwrap();
+                prepped.check_collision(&static_collision)
+            })
+    };
+
+    if let Some(max_slope) = limits.max_slope {
+        if normal.y > 0. && normal.y >= max_slope.cos() {
+            // Rise needed to clear a slope of this angle, with some leeway for the contact normal
+            let slope_tan = (1. - normal.y * normal.y).sqrt() / normal.y;
+            let max_lift = movement.length() * slope_tan * SLOPE_LIFT_LEEWAY + SLOPE_LIFT_EPSILON;
+
+            if is_free(start + movement + glam::Vec3::Y * max_lift) {
+                let mut free = max_lift;
+                let mut blocked = 0.;
+
+                for _ in 0..AXIS_CONTACT_STEPS {
+                    let mid = (free + blocked) * 0.5;
+
+                    match is_free(start + movement + glam::Vec3::Y * mid) {
+                        true => free = mid,
+                        false => blocked = mid,
+                    }
+                }
+
+                transform.translation = start + movement + glam::Vec3::Y * free;
+                return true;
+            }
+        }
+    }
+
+    let step_height = match limits.step_height {
+        Some(step_height) => step_height,
+        None => return false,
+    };
+
+    // Only step over the vertical face of a ledge, not up steep slopes
+    if normal.y.abs() > STEP_WALL_NORMAL_Y {
+        return false;
+    }
+
+    let raised = start + glam::Vec3::Y * step_height;
+
+    if !is_free(raised) || !is_free(raised + movement) {
+        return false;
+    }
+
+    transform.translation = raised + movement;
+
+    let down = glam::Vec3::NEG_Y * step_height;
+    let candidates = nearby_statics(statics, transform, shape, down);
+    let travel = find_axis_contact(world, transform, shape, down, &candidates);
+    transform.translation += down * travel;
+
+    true
+}
+
+fn snap_to_ground(
+    world: &World,
+    statics: &StaticQuery,
+    dynamic_entity: Entity,
+    transform: &mut Transform,
+    shape: &CollisionShape,
+    hits: &mut CollisionHits,
+    snap_distance: f32,
+) {
+    if hits
+        .hits
+        .iter()
+        .any(|(_, direction)| *direction == CollisionDirection::PosY)
+    {
+        return;
+    }
+
+    let down = glam::Vec3::NEG_Y * snap_distance;
+    let candidates = nearby_statics(statics, transform, shape, down);
+
+    if candidates.is_empty() {
+        return;
+    }
+
+    let travel = find_axis_contact(world, transform, shape, down, &candidates);
+
+    // Nothing within reach so we're walking off a ledge rather than down a slope
+    if travel >= 1. - 1. / (1 << AXIS_CONTACT_STEPS) as f32 {
+        return;
+    }
+
+    transform.translation += down * travel;
+
+    let below = prep_dynamic_shape(
+        shape,
+        transform.translation + down / (1 << AXIS_CONTACT_STEPS) as f32,
+        transform,
+    );
+
+    candidates.into_iter().for_each(|static_entity| {
+        let static_collision = world.get::<&PreppedCollisionShape>(static_entity).unwrap();
+
+        if below.check_collision(&static_collision) {
+            hits.hits.push((static_entity, CollisionDirection::PosY));
+
+            if let Ok(mut static_hits) = world.get::<&mut CollisionHits>(static_entity) {
+                static_hits
+                    .hits
+                    .push((dynamic_entity, CollisionDirection::NegY));
+            }
+        }
+    });
+}
+
+#[inline]
+fn nearby_statics(
+    statics: &StaticQuery,
+    transform: &Transform,
+    shape: &CollisionShape,
+    movement: glam::Vec3,
+) -> Vec<Entity> {
+    let start = prep_dynamic_shape(shape, transform.translation, transform).bounds();
+    let end = Aabb::new(start.min + movement, start.max + movement);
+
+    statics.query(&start.union(&end))
+}
+
+fn deepest_contact(
+    world: &World,
+    prepped: &PreppedCollisionShape,
+    check_against: &[Entity],
+) -> Option<Contact> {
+    check_against
+        .iter()
+        .filter_map(|static_entity| {
+            let static_collision = world.get::<&PreppedCollisionShape>(*static_entity).unwrap();
+            prepped.contact(&static_collision)
+        })
+        .max_by(|a, b| a.depth.total_cmp(&b.depth))
+}
+
+fn find_axis_contact(
+    world: &World,
+    transform: &Transform,
+    shape: &CollisionShape,
+    movement: glam::Vec3,
+    check_against: &[Entity],
+) -> f32 {
+    let collides_at = |travel: f32| {
+        let prepped =
+            prep_dynamic_shape(shape, transform.translation + movement * travel, transform);
+
+        check_against.iter().any(|static_entity| {
+            let static_collision = world.get::<&PreppedCollisionShape>(*static_entity).unwrap();
+
+            prepped.check_collision(&static_collision)
+        })
+    };
+
+    let mut free = 0.;
+    let mut blocked = 1.;
+
+    for _ in 0..AXIS_CONTACT_STEPS {
+        let mid = (free + blocked) * 0.5;
+
+        match collides_at(mid) {
+            true => blocked = mid,
+            false => free = mid,
+        }
+    }
+
+    free
+}
+
+fn resolve_dynamic_collisions(world: &mut World, grid: &SpatialGrid) {
+    let mut dynamics = world
+        .query::<(
+            &Transform,
+            &CollisionShape,
+            Option<&PushWeight>,
+            Option<&CollisionLayers>,
+        )>()
+        .with::<&DynamicCollisionType>()
+        .without::<&StaticCollisionType>()
+        .without::<&TriggerCollisionType>()
+        .without::<&RigidBody>()
+        .into_iter()
+        .map(|(entity, (transform, shape, weight, layers))| {
+            let prepped = prep_dynamic_shape(shape, transform.translation, transform);
+            let weight = weight.map(|weight| weight.0).unwrap_or(1.);
+
+            (entity, prepped, weight, layers.copied().unwrap_or_default())
+        })
+        .collect::<Vec<_>>();
+
+    for a_index in 0..dynamics.len() {
+        for b_index in a_index + 1..dynamics.len() {
+            let (a, a_shape, a_weight, a_layers) = &dynamics[a_index];
+            let (b, b_shape, b_weight, b_layers) = &dynamics[b_index];
+            let (a, b) = (*a, *b);
+
+            if !a_layers.interacts_with(b_layers) {
+                continue;
+            }
+
+            let a_bounds = a_shape.bounds();
+            let b_bounds = b_shape.bounds();
+
+            if !a_bounds.intersects(&b_bounds) || !a_shape.check_collision(b_shape) {
+                continue;
+            }
+
+            // Separate along the axis of least overlap, pointing from b to a
+            let overlap = a_bounds.max.min(b_bounds.max) - a_bounds.min.max(b_bounds.min);
+            let (axis, depth) = [
+                (Axis::X, overlap.x),
+                (Axis::Y, overlap.y),
+                (Axis::Z, overlap.z),
+            ]
+            .into_iter()
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .unwrap();
+
+            let center_offset = (a_bounds.min + a_bounds.max) - (b_bounds.min + b_bounds.max);
+            let separation = match center_offset.dot(axis.get_scale()) >= 0. {
+                true => axis.get_scale() * depth,
+                false => -axis.get_scale() * depth,
+            };
+
+            let a_share = match a_weight + b_weight > 0. {
+                true => b_weight / (a_weight + b_weight),
+                false => 0.5,
+            };
+
+            // Whatever one side can't take because of a wall is handed to the other
+            let b_moved = push_dynamic(world, grid, b, -separation * (1. - a_share));
+            push_dynamic(world, grid, a, separation + b_moved);
+
+            let direction = CollisionDirection::from_axis(axis, &separation);
+
+            if let Ok(mut hits) = world.get::<&mut CollisionHits>(a) {
+                hits.hits.push((b, direction));
+            }
+
+            if let Ok(mut hits) = world.get::<&mut CollisionHits>(b) {
+                hits.hits.push((a, direction.flip()));
+            }
+
+            [a_index, b_index].into_iter().for_each(|index| {
+                let entity = dynamics[index].0;
+                let transform = world.get::<&Transform>(entity).unwrap();
+                let shape = world.get::<&CollisionShape>(entity).unwrap();
+
+                dynamics[index].1 = prep_dynamic_shape(&shape, transform.translation, &transform);
+            });
+        }
+    }
+}
+
+// Moves a dynamic entity without pushing it into static geometry. Returns how far it moved.
+fn push_dynamic(
+    world: &World,
+    grid: &SpatialGrid,
+    entity: Entity,
+    offset: glam::Vec3,
+) -> glam::Vec3 {
+    if offset == glam::Vec3::ZERO {
+        return glam::Vec3::ZERO;
+    }
+
+    let shape = world.get::<&CollisionShape>(entity).unwrap();
+    let mut transform = world.get::<&mut Transform>(entity).unwrap();
+
+    let target = prep_dynamic_shape(&shape, transform.translation + offset, &transform);
+
+    let statics = StaticQuery {
+        world,
+        grid,
+        bodies: &[],
+        layers: CollisionLayers::of(world, entity),
+    };
+
+    let blocking = statics
+        .query(&target.bounds())
+        .into_iter()
+        .filter(|static_entity| {
+            let static_collision = world.get::<&PreppedCollisionShape>(*static_entity).unwrap();
+            target.check_collision(&static_collision)
+        })
+        .collect::<Vec<_>>();
+
+    let travel = match blocking.is_empty() {
+        true => 1.,
+        false => find_axis_contact(world, &transform, &shape, offset, &blocking),
+    };
+
+    transform.translation += offset * travel;
+    offset * travel
+}
+
+// Dynamics keep an upright, axis aligned collider whichever way they are facing
+#[inline]
+fn prep_dynamic_shape(
+    shape: &CollisionShape,
+    translation: glam::Vec3,
+    transform: &Transform,
+) -> PreppedCollisionShape {
+    PreppedCollisionShape::from_collision_shape(
+        shape,
+        translation,
+        glam::Quat::IDENTITY,
+        transform.scale,
+    )
+}
+
+// Rigid bodies are the exception and rotate their collider along with them
+#[inline]
+fn prep_rigid_body_shape(shape: &CollisionShape, transform: &Transform) -> PreppedCollisionShape {
+    PreppedCollisionShape::from_collision_shape(
+        shape,
+        transform.translation,
+        transform.rotation,
+        transform.scale,
+    )
+}
+
+// Prep any kind of collider as it is right now, shifted by offset
+fn prep_entity_shape(
+    world: &World,
+    entity: Entity,
+    offset: glam::Vec3,
+) -> Option<PreppedCollisionShape> {
+    let entity_ref = world.entity(entity).ok()?;
+    let shape = entity_ref.get::<&CollisionShape>()?;
+
+    // Dynamics move themselves so only ever go by their own Transform
+    if entity_ref.has::<DynamicCollisionType>() {
+        let transform = entity_ref.get::<&Transform>()?;
+        let translation = transform.translation + offset;
+
+        return Some(match entity_ref.has::<RigidBody>() {
+            true => PreppedCollisionShape::from_collision_shape(
+                &shape,
+                translation,
+                transform.rotation,
+                transform.scale,
+            ),
+            false => prep_dynamic_shape(&shape, translation, &transform),
+        });
+    }
+
+    let transform = world_transform(world, entity)?;
+
+    Some(PreppedCollisionShape::from_collision_shape(
+        &shape,
+        transform.translation + offset,
+        transform.rotation,
+        transform.scale,
+    ))
+}
+
+// Where a static, kinematic or trigger collider sits in the world. Children attached
+// with a LocalTransform are placed relative to their parent rather than by their own Transform.
+fn world_transform(world: &World, entity: Entity) -> Option<Transform> {
+    let mut locals = Vec::new();
+    let mut current = entity;
+
+    while let Ok(local) = world.get::<&LocalTransform>(current) {
+        if locals.len() == MAX_PARENT_DEPTH {
+            return None;
+        }
+
+        locals.push((
+            local.transform.translation,
+            local.transform.rotation,
+            local.transform.scale,
+        ));
+        current = local.parent;
+    }
+
+    let root = world.get::<&Transform>(current).ok()?;
+
+    let (translation, rotation, scale) = locals.into_iter().rev().fold(
+        (root.translation, root.rotation, root.scale),
+        |(translation, rotation, scale), (local_translation, local_rotation, local_scale)| {
+            (
+                translation + rotation * (scale * local_translation),
+                rotation * local_rotation,
+                scale * local_scale,
+            )
+        },
+    );
+
+    let mut transform = Transform::from_rotation_translation(rotation, translation);
+    transform.scale = scale;
+
+    Some(transform)
+}
+
+fn has_moved_parent(world: &World, parent: Entity, moved: &HashSet<Entity>) -> bool {
+    let mut current = parent;
+
+    for _ in 0..MAX_PARENT_DEPTH {
+        if moved.contains(&current) {
+            return true;
+        }
+
+        current = match world.get::<&LocalTransform>(current) {
+            Ok(local) => local.parent,
+            Err(_) => return false,
+        };
+    }
+
+    false
+}
+
+fn update_grounded(world: &mut World) {
+    let remove_grounded = world
+        .query_mut::<&CollisionHits>()
+        .with::<(&Gravity, &Grounded)>()
+        .into_iter()
+        .filter_map(|(entity, hits)| {
+            match hits
+                .hits
+                .iter()
+                .any(|(_, collision)| *collision == CollisionDirection::PosY)
+            {
+                true => None,
+                false => Some(entity),
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Anything moving upwards has just bounced off what it landed on
+    let add_grounded = world
+        .query_mut::<(&CollisionHits, Option<&Velocity>)>()
+        .with::<&Gravity>()
+        .without::<&Grounded>()
+        .without::<&RigidBody>()
+        .into_iter()
+        .filter_map(|(entity, (hits, velocity))| {
+            match hits
+                .hits
+                .iter()
+                .any(|(_, collision)| *collision == CollisionDirection::PosY)
+                && velocity.map(|velocity| velocity.0.y <= 0.).unwrap_or(true)
+            {
+                true => Some(entity),
+                false => None,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    remove_grounded.into_iter().for_each(|entity| {
+        world.remove_one::<Grounded>(entity).unwrap();
+    });
+
+    add_grounded.into_iter().for_each(|entity| {
+        world.insert_one(entity, Grounded).unwrap();
+    });
+}
+
+fn apply_surface_bounce(world: &mut World) {
+    world
+        .query::<(&CollisionHits, &mut Velocity)>()
+        .with::<&Gravity>()
+        .without::<&Grounded>()
+        .without::<&RigidBody>()
+        .into_iter()
+        .for_each(|(_, (hits, velocity))| {
+            let bounce = ground_material(world, hits)
+                .map(|material| material.bounce)
+                .unwrap_or(0.);
+
+            if bounce > 0. && velocity.0.y < -MIN_BOUNCE_SPEED {
+                velocity.0.y = -velocity.0.y * bounce;
+            }
+        });
+}
+
+fn update_ground_surfaces(world: &mut World) {
+    let grounded = world
+        .query::<&CollisionHits>()
+        .with::<&Grounded>()
+        .into_iter()
+        .map(|(entity, hits)| {
+            (
+                entity,
+                ground_material(world, hits),
+                ground_normal(world, entity, hits),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    let airborne = world
+        .query::<()>()
+        .with::<&GroundNormal>()
+        .without::<&Grounded>()
+        .into_iter()
+        .map(|(entity, _)| entity)
+        .collect::<Vec<_>>();
+
+    grounded.into_iter().for_each(|(entity, material, normal)| {
+        match material {
+            Some(material) => world.insert_one(entity, GroundSurface(material)).unwrap(),
+            None => {
+                world.remove_one::<GroundSurface>(entity).ok();
+            }
+        }
+
+        world.insert_one(entity, GroundNormal(normal)).unwrap();
+    });
+
+    airborne.into_iter().for_each(|entity| {
+        world.remove_one::<GroundSurface>(entity).ok();
+        world.remove_one::<GroundNormal>(entity).ok();
+    });
+}
+
+// Normal of the deepest upward facing contact just below the entity, or straight up
+// if it's grounded on something that can't be probed
+fn ground_normal(world: &World, entity: Entity, hits: &CollisionHits) -> glam::Vec3 {
+    let probe = match prep_entity_shape(world, entity, glam::Vec3::NEG_Y * GROUND_PROBE_DEPTH) {
+        Some(probe) => probe,
+        None => return glam::Vec3::Y,
+    };
+
+    hits.hits
+        .iter()
+        .filter(|(_, direction)| *direction == CollisionDirection::PosY)
+        .filter_map(|(other, _)| {
+            let ground = world.get::<&PreppedCollisionShape>(*other).ok()?;
+            probe.contact(&ground)
+        })
+        .filter(|contact| contact.normal.y > 0.)
+        .max_by(|a, b| a.depth.total_cmp(&b.depth))
+        .map(|contact| contact.normal)
+        .unwrap_or(glam::Vec3::Y)
+}
+
+#[inline]
+fn ground_material(world: &World, hits: &CollisionHits) -> Option<PhysicsMaterial> {
+    hits.hits
+        .iter()
+        .filter(|(_, direction)| *direction == CollisionDirection::PosY)
+        .find_map(|(other, _)| {
+            world
+                .get::<&PhysicsMaterial>(*other)
+                .ok()
+                .map(|material| *material)
+        })
+}
+
+fn restore_physics_transforms(world: &mut World) {
+    let new_entities = world
+        .query_mut::<&Transform>()
+        .with::<&Velocity>()
+        .without::<&PhysicsInterpolation>()
+        .into_iter()
+        .map(|(entity, transform)| (entity, PhysicsInterpolation::new(transform)))
+        .collect::<Vec<_>>();
+
+    new_entities
+        .into_iter()
+        .for_each(|(entity, interpolation)| {
+            world.insert_one(entity, interpolation).unwrap();
+        });
+
+    world
+        .query_mut::<(&mut Transform, &mut PhysicsInterpolation)>()
+        .into_iter()
+        .for_each(|(_, (transform, interpolation))| {
+            match transform.translation == interpolation.rendered
+                && transform.rotation == interpolation.rendered_rotation
+            {
+                // Put back the real physics position
+                true => {
+                    transform.translation = interpolation.current;
+                    transform.rotation = interpolation.current_rotation;
+                }
+
+                // Moved by something other than physics, so teleport rather than blend
+                false => *interpolation = PhysicsInterpolation::new(transform),
+            }
+        });
+}
+
+// Keep blending between the same two points after being moved outside of a step
+#[inline]
+fn shift_interpolation(world: &World, entity: Entity, offset: glam::Vec3) {
+    if let Ok(mut interpolation) = world.get::<&mut PhysicsInterpolation>(entity) {
+        interpolation.previous += offset;
+        interpolation.current += offset;
+    }
+}
+
+#[inline]
+fn store_previous_transforms(world: &mut World) {
+    world
+        .query_mut::<(&Transform, &mut PhysicsInterpolation)>()
+        .into_iter()
+        .for_each(|(_, (transform, interpolation))| {
+            interpolation.previous = transform.translation;
+            interpolation.previous_rotation = transform.rotation;
+        });
+}
+
+#[inline]
+fn interpolate_physics_transforms(world: &mut World, alpha: f32) {
+    world
+        .query_mut::<(&mut Transform, &mut PhysicsInterpolation)>()
+        .into_iter()
+        .for_each(|(_, (transform, interpolation))| {
+            interpolation.current = transform.translation;
+            interpolation.rendered = interpolation.previous.lerp(interpolation.current, alpha);
+
+            interpolation.current_rotation = transform.rotation;
+            interpolation.rendered_rotation = interpolation
+                .previous_rotation
+                .slerp(interpolation.current_rotation, alpha);
+
+            transform.translation = interpolation.rendered;
+            transform.rotation = interpolation.rendered_rotation;
+        });
+}
+
+// Move is held input, so frames rendered without a step in between shouldn't stack up
+// and multiply the acceleration. One shot actions are kept until a step uses them.
+fn keep_latest_move(world: &mut World) {
+    world
+        .query_mut::<&mut CharacterController>()
+        .into_iter()
+        .for_each(|(_, controller)| {
+            let latest = controller
+                .movement_action_queue
+                .iter()
+                .rposition(|action| matches!(action, MovementAction::Move(_)));
+
+            let mut index = 0;
+
+            controller.movement_action_queue.retain(|action| {
+                let keep = !matches!(action, MovementAction::Move(_)) || Some(index) == latest;
+                index += 1;
+                keep
+            });
+        });
+}
+
+#[inline]
+fn consume_one_shot_actions(world: &mut World) {
+    world
+        .query_mut::<&mut CharacterController>()
+        .into_iter()
+        .for_each(|(_, controller)| {
+            controller
+                .movement_action_queue
+                .retain(|action| matches!(action, MovementAction::Move(_)))
+        });
+}
+
+#[inline]
+fn clear_controller_actions(world: &mut World) {
+    world
+        .query_mut::<&mut CharacterController>()
+        .into_iter()
+        .for_each(|(_, controller)| controller.movement_action_queue.clear());
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    pub(super) const TICK: f32 = 1. / 60.;
+
+    pub(super) fn cube(half_size: f32) -> CollisionShape {
+        CollisionShape::Box {
+            half_width: half_size,
+            half_height: half_size,
+            half_depth: half_size,
+        }
+    }
+
+    pub(super) fn spawn_static(
+        world: &mut World,
+        translation: glam::Vec3,
+        shape: CollisionShape,
+    ) -> Entity {
+        world.spawn((
+            Transform::from_translation(translation),
+            StaticCollisionType,
+            shape,
+        ))
+    }
+
+    // Gravity and damping free, so it keeps going until something stops it
+    pub(super) fn spawn_mover(
+        world: &mut World,
+        translation: glam::Vec3,
+        velocity: glam::Vec3,
+    ) -> Entity {
+        let entity = world.spawn(CharacterCollisionBundle::from_shape(cube(5.)));
+        world
+            .insert(
+                entity,
+                (Transform::from_translation(translation), Velocity(velocity)),
+            )
+            .unwrap();
+
+        entity
+    }
+
+    pub(super) fn run(physics: &mut PhysicsHandler, world: &mut World, ticks: u32) {
+        (0..ticks).for_each(|_| physics.tick(world, TICK));
+    }
+
+    #[inline]
+    pub(super) fn translation(world: &World, entity: Entity) -> glam::Vec3 {
+        world.get::<&Transform>(entity).unwrap().translation
+    }
+
+    pub(super) fn spawn_floor(world: &mut World) -> Entity {
+        spawn_static(
+            world,
+            glam::vec3(0., -10., 0.),
+            CollisionShape::Box {
+                half_width: 500.,
+                half_height: 10.,
+                half_depth: 500.,
+            },
+        )
+    }
+
+    pub(super) fn spawn_character(world: &mut World, translation: glam::Vec3) -> Entity {
+        let entity = world.spawn(CharacterMovementBundle::default());
+        world
+            .insert(entity, CharacterCollisionBundle::default())
+            .unwrap();
+        world
+            .insert_one(entity, Transform::from_translation(translation))
+            .unwrap();
+
+        entity
+    }
+
+    #[inline]
+    pub(super) fn push_action(world: &World, entity: Entity, action: MovementAction) {
+        world
+            .get::<&mut CharacterController>(entity)
+            .unwrap()
+            .movement_action_queue
+            .push(action);
+    }
+
+    #[test]
+    fn despawned_static_stops_blocking() {
+        let mut world = World::new();
+        let wall = spawn_static(&mut world, glam::vec3(50., 0., 0.), cube(10.));
+        let mover = spawn_mover(&mut world, glam::Vec3::ZERO, glam::vec3(600., 0., 0.));
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 1);
+
+        world.despawn(wall).unwrap();
+        run(&mut physics, &mut world, 30);
+
+        assert!(translation(&world, mover).x > 100.);
+    }
+
+    #[test]
+    fn moved_static_blocks_where_it_is() {
+        let mut world = World::new();
+        let wall = spawn_static(&mut world, glam::vec3(50., 0., 0.), cube(10.));
+
+        // Attached by LocalTransform, so moving its parent moves it too
+        let parent = world.spawn((Transform::from_translation(glam::vec3(0., 0., 100.)),));
+        let child = world.spawn((
+            Transform::default(),
+            LocalTransform {
+                parent,
+                transform: Transform::default(),
+            },
+            StaticCollisionType,
+            cube(10.),
+        ));
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 1);
+
+        world.get::<&mut Transform>(wall).unwrap().translation.x = 200.;
+        world.get::<&mut Transform>(parent).unwrap().translation = glam::vec3(0., 0., 50.);
+
+        let along_x = spawn_mover(
+            &mut world,
+            glam::vec3(100., 0., 0.),
+            glam::vec3(600., 0., 0.),
+        );
+        let along_z = spawn_mover(&mut world, glam::Vec3::ZERO, glam::vec3(0., 0., 600.));
+        run(&mut physics, &mut world, 30);
+
+        let stopped_x = translation(&world, along_x).x;
+        let stopped_z = translation(&world, along_z).z;
+
+        assert!((stopped_x - 185.).abs() < 0.5, "{stopped_x}");
+        assert!((stopped_z - 35.).abs() < 0.5, "{stopped_z}");
+        assert!(world.get::<&PreppedCollisionShape>(child).is_ok());
+    }
+
+    #[test]
+    fn compound_child_follows_parent_rotation() {
+        let mut world = World::new();
+        let parent = world.spawn((Transform::from_rotation_translation(
+            glam::Quat::from_rotation_y(90_f32.to_radians()),
+            glam::vec3(100., 0., 0.),
+        ),));
+
+        // Parts are along the child's x, which the parent turns to run along z
+        world.spawn((
+            Transform::default(),
+            LocalTransform {
+                parent,
+                transform: Transform::default(),
+            },
+            StaticCollisionType,
+            CollisionShape::Compound(vec![
+                (glam::vec3(50., 0., 0.), cube(10.)),
+                (glam::vec3(-50., 0., 0.), cube(10.)),
+            ]),
+        ));
+
+        let forward = spawn_mover(
+            &mut world,
+            glam::vec3(100., 0., 0.),
+            glam::vec3(0., 0., 600.),
+        );
+        let back = spawn_mover(
+            &mut world,
+            glam::vec3(100., 0., 0.),
+            glam::vec3(0., 0., -600.),
+        );
+
+        // Where a part would be if the offset ignored the parent's rotation
+        let unrotated = spawn_mover(
+            &mut world,
+            glam::vec3(150., 0., -100.),
+            glam::vec3(0., 0., 600.),
+        );
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 30);
+
+        let forward = translation(&world, forward).z;
+        let back = translation(&world, back).z;
+
+        assert!((forward - 35.).abs() < 0.5, "{forward}");
+        assert!((back + 35.).abs() < 0.5, "{back}");
+        assert!(translation(&world, unrotated).z > 100.);
+    }
+
+    #[test]
+    fn spawned_overlapping_is_pushed_out() {
+        let mut world = World::new();
+        spawn_floor(&mut world);
+        spawn_static(&mut world, glam::vec3(50., 30., 0.), cube(10.));
+
+        // Sunk into the floor, and mostly inside the wall's left half
+        let sunk = spawn_character(&mut world, glam::vec3(-100., 15., 0.));
+        let inside = spawn_mover(&mut world, glam::vec3(45., 30., 0.), glam::Vec3::ZERO);
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 1);
+
+        // Out the shortest way rather than through the far side
+        let inside_at = translation(&world, inside);
+        assert!((inside_at.x - 35.).abs() < 0.5, "{inside_at}");
+        assert!((translation(&world, sunk).y - 20.).abs() < 0.5);
+
+        // And free to move again
+        (0..30).for_each(|_| {
+            push_action(&world, sunk, MovementAction::Move((1., 0.)));
+            physics.tick(&mut world, TICK);
+        });
+        assert!(translation(&world, sunk).x > -80.);
+    }
+
+    #[test]
+    fn crouch_blocked_by_low_ceiling() {
+        let mut world = World::new();
+        spawn_floor(&mut world);
+        let character = spawn_character(&mut world, glam::vec3(0., 21., 0.));
+        world
+            .insert_one(
+                character,
+                CrouchControl {
+                    height: 0.5,
+                    speed: 0.5,
+                },
+            )
+            .unwrap();
+
+        let half_height = |world: &World| match *world.get::<&CollisionShape>(character).unwrap() {
+            CollisionShape::Box { half_height, .. } => half_height,
+            _ => unreachable!(),
+        };
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 30);
+
+        push_action(&world, character, MovementAction::Crouch);
+        run(&mut physics, &mut world, 10);
+
+        // Feet stay on the floor
+        assert_eq!(half_height(&world), 10.);
+        assert!((translation(&world, character).y - 10.).abs() < 0.5);
+
+        // Low enough to only fit while crouched
+        let ceiling = spawn_static(
+            &mut world,
+            glam::vec3(0., 40., 0.),
+            CollisionShape::Box {
+                half_width: 100.,
+                half_height: 10.,
+                half_depth: 100.,
+            },
+        );
+
+        push_action(&world, character, MovementAction::CrouchRelease);
+        run(&mut physics, &mut world, 30);
+        assert_eq!(half_height(&world), 10.);
+
+        // Stands once there's room without needing another release
+        world.despawn(ceiling).unwrap();
+        run(&mut physics, &mut world, 10);
+
+        assert_eq!(half_height(&world), 20.);
+        assert!((translation(&world, character).y - 20.).abs() < 0.5);
+    }
+
+    // Accelerate for a second then let go for a second, checking the velocity every half
+    // second. Positions are only compared once stopped as they drift by a step mid-way.
+    fn accelerate_then_stop(tick_rate: u32, max_speed: Option<f32>) -> (Vec<glam::Vec3>, f32) {
+        let mut world = World::new();
+        let entity = world.spawn(CharacterMovementBundle::default());
+        world.insert_one(entity, Grounded).unwrap();
+
+        if let Some(max_speed) = max_speed {
+            world.insert_one(entity, MaxSpeed(max_speed)).unwrap();
+        }
+
+        let delta_time = 1. / tick_rate as f32;
+        let mut position = 0.;
+        let mut checkpoints = Vec::new();
+
+        (0..tick_rate * 2).for_each(|step| {
+            world
+                .get::<&mut CharacterController>(entity)
+                .unwrap()
+                .movement_action_queue = match step < tick_rate {
+                true => vec![MovementAction::Move((1., 0.))],
+                false => Vec::new(),
+            };
+
+            apply_character_movement(&mut world, delta_time);
+            apply_damping(&mut world, delta_time);
+
+            let velocity = world.get::<&Velocity>(entity).unwrap().0;
+            position += velocity.x * delta_time;
+
+            if (step + 1) % (tick_rate / 2) == 0 {
+                checkpoints.push(velocity);
+            }
+        });
+
+        (checkpoints, position)
+    }
+
+    #[test]
+    fn damping_independent_of_tick_rate() {
+        [None, Some(150.)].into_iter().for_each(|max_speed| {
+            let (expected_velocities, expected_position) = accelerate_then_stop(60, max_speed);
+
+            [30, 144].into_iter().for_each(|tick_rate| {
+                let (velocities, position) = accelerate_then_stop(tick_rate, max_speed);
+
+                assert_eq!(velocities.len(), expected_velocities.len());
+                velocities
+                    .iter()
+                    .zip(&expected_velocities)
+                    .for_each(|(velocity, expected)| {
+                        assert!(
+                            velocity.abs_diff_eq(*expected, 0.01),
+                            "{tick_rate}hz: {velocity} != {expected}"
+                        );
+                    });
+
+                assert!(
+                    (position - expected_position).abs() < 0.05,
+                    "{tick_rate}hz: {position} != {expected_position}"
+                );
+            });
+        });
+    }
+
+    // Holding move for a second should end up in the same place however often frames
+    // are rendered, whether that's several frames per step or several steps per frame.
+    fn hold_move(render_delta: f32) -> (glam::Vec3, glam::Vec3) {
+        let mut world = World::new();
+        let entity = world.spawn(CharacterMovementBundle::default());
+        world
+            .insert_one(entity, Transform::from_translation(glam::Vec3::ZERO))
+            .unwrap();
+        world
+            .insert(entity, CharacterCollisionBundle::default())
+            .unwrap();
+
+        let mut physics = PhysicsHandler::new(64., 5);
+
+        (0..(1. / render_delta) as u32).for_each(|_| {
+            world
+                .get::<&mut CharacterController>(entity)
+                .unwrap()
+                .movement_action_queue
+                .push(MovementAction::Move((1., 0.)));
+
+            physics.tick(&mut world, render_delta);
+        });
+
+        let velocity = world.get::<&Velocity>(entity).unwrap().0;
+        let translation = world.get::<&PhysicsInterpolation>(entity).unwrap().current;
+
+        (velocity, translation)
+    }
+
+    #[test]
+    fn movement_independent_of_frame_rate() {
+        let (expected_velocity, expected_translation) = hold_move(1. / 64.);
+        assert!(expected_velocity.x > 0.);
+
+        [1. / 128., 1. / 32.].into_iter().for_each(|render_delta| {
+            let (velocity, translation) = hold_move(render_delta);
+
+            assert!(
+                velocity.abs_diff_eq(expected_velocity, 0.01),
+                "{render_delta}: {velocity} != {expected_velocity}"
+            );
+            assert!(
+                translation.abs_diff_eq(expected_translation, 0.01),
+                "{render_delta}: {translation} != {expected_translation}"
+            );
+        });
+    }
+
+    #[test]
+    fn rotation_blended_between_steps() {
+        let mut world = World::new();
+        let body = world.spawn(RigidBodyBundle::new(cube(10.), 1.));
+        world
+            .insert(
+                body,
+                (
+                    Transform::from_translation(glam::Vec3::ZERO),
+                    Gravity(glam::Vec3::ZERO),
+                    AngularVelocity(glam::vec3(0., 3., 0.)),
+                ),
+            )
+            .unwrap();
+
+        let mut physics = PhysicsHandler::new(60., 4);
+        run(&mut physics, &mut world, 2);
+
+        // Leaves half a step in the accumulator
+        physics.tick(&mut world, TICK * 1.5);
+
+        let rendered = world.get::<&Transform>(body).unwrap().rotation;
+        let interpolation = world.get::<&PhysicsInterpolation>(body).unwrap();
+        let from_previous = rendered.angle_between(interpolation.previous_rotation);
+        let to_current = rendered.angle_between(interpolation.current_rotation);
+
+        assert!(from_previous > 0.01, "{from_previous}");
+        assert!((from_previous - to_current).abs() < 0.001);
+    }
+
+    #[test]
+    fn platform_carries_rider() {
+        let mut world = World::new();
+        let platform = world.spawn((
+            Transform::from_translation(glam::vec3(0., -10., 0.)),
+            KinematicCollisionType,
+            CollisionShape::Box {
+                half_width: 100.,
+                half_height: 10.,
+                half_depth: 100.,
+            },
+        ));
+        let rider = spawn_character(&mut world, glam::vec3(0., 21., 0.));
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 30);
+
+        let start = translation(&world, rider);
+        assert!(world.get::<&Grounded>(rider).is_ok());
+
+        (0..60).for_each(|_| {
+            world.get::<&mut Transform>(platform).unwrap().translation.x += 2.;
+            physics.tick(&mut world, TICK);
+        });
+
+        let carried = translation(&world, rider) - start;
+        assert!((carried.x - 120.).abs() < 1., "{carried}");
+        assert!(carried.y.abs() < 1., "{carried}");
+        assert!(world.get::<&Grounded>(rider).is_ok());
+    }
+
+    // How far a mover running into another entity of the given weight pushes it
+    fn push_into(weight: f32) -> f32 {
+        let mut world = World::new();
+        let pusher = spawn_mover(&mut world, glam::Vec3::ZERO, glam::vec3(300., 0., 0.));
+        let pushed = spawn_mover(&mut world, glam::vec3(30., 0., 0.), glam::Vec3::ZERO);
+        world.insert_one(pushed, PushWeight(weight)).unwrap();
+
+        let mut physics = PhysicsHandler::default();
+
+        (0..30).for_each(|_| {
+            physics.tick(&mut world, TICK);
+
+            let gap = translation(&world, pushed).x - translation(&world, pusher).x;
+            assert!(gap > 9.9, "{gap}");
+        });
+
+        let hit = |entity: Entity, other: Entity| {
+            world
+                .get::<&CollisionHits>(entity)
+                .unwrap()
+                .hits
+                .iter()
+                .any(|(hit, _)| *hit == other)
+        };
+        assert!(hit(pusher, pushed));
+        assert!(hit(pushed, pusher));
+
+        translation(&world, pushed).x - 30.
+    }
+
+    #[test]
+    fn dynamics_push_by_weight() {
+        let light = push_into(1.);
+        let heavy = push_into(4.);
+
+        assert!(heavy > 0., "{heavy}");
+        assert!(heavy < light * 0.6, "{heavy} {light}");
+    }
+
+    // Where a character ends up after walking in +x for two seconds
+    fn walk_over(
+        world: &mut World,
+        step_height: Option<f32>,
+        max_slope: Option<f32>,
+    ) -> glam::Vec3 {
+        spawn_floor(world);
+        let character = spawn_character(world, glam::vec3(0., 21., 0.));
+
+        if let Some(step_height) = step_height {
+            world
+                .insert_one(character, MaxStepHeight(step_height))
+                .unwrap();
+        }
+
+        if let Some(max_slope) = max_slope {
+            world
+                .insert_one(character, MaxSlopeAngle(max_slope))
+                .unwrap();
+        }
+
+        let mut physics = PhysicsHandler::default();
+
+        (0..120).for_each(|_| {
+            push_action(world, character, MovementAction::Move((1., 0.)));
+            physics.tick(world, TICK);
+        });
+
+        translation(world, character)
+    }
+
+    fn spawn_ramp(world: &mut World, angle: f32) {
+        let (sin, cos) = angle.sin_cos();
+
+        // Bottom end starts at x=60, with its top face level with the floor
+        world.spawn((
+            Transform::from_rotation_translation(
+                glam::Quat::from_rotation_z(angle),
+                glam::vec3(60. + 200. * cos, 200. * sin - 2. * cos, 0.),
+            ),
+            StaticCollisionType,
+            CollisionShape::Box {
+                half_width: 200.,
+                half_height: 2.,
+                half_depth: 100.,
+            },
+        ));
+    }
+
+    #[test]
+    fn steps_up_low_ledges() {
+        let spawn_ledge = |world: &mut World| {
+            world.spawn((
+                Transform::from_translation(glam::vec3(460., 3., 0.)),
+                StaticCollisionType,
+                CollisionShape::Box {
+                    half_width: 400.,
+                    half_height: 3.,
+                    half_depth: 100.,
+                },
+            ));
+        };
+
+        let mut world = World::new();
+        spawn_ledge(&mut world);
+        let blocked = walk_over(&mut world, None, None);
+        assert!(blocked.x < 40.1, "{blocked}");
+
+        let mut world = World::new();
+        spawn_ledge(&mut world);
+        let climbed = walk_over(&mut world, Some(8.), None);
+        assert!(climbed.x > 100., "{climbed}");
+        assert!((climbed.y - 26.).abs() < 1., "{climbed}");
+    }
+
+    #[test]
+    fn walks_up_slopes_within_max_angle() {
+        let climb = |angle: f32, max_slope: Option<f32>| {
+            let mut world = World::new();
+            spawn_ramp(&mut world, angle.to_radians());
+            walk_over(&mut world, Some(8.), max_slope.map(f32::to_radians))
+        };
+
+        let shallow = climb(20., Some(45.));
+        assert!(shallow.x > 120., "{shallow}");
+        assert!(shallow.y > 40., "{shallow}");
+
+        let within = climb(35., Some(45.));
+        assert!(within.y > 40., "{within}");
+
+        let too_steep = climb(35., Some(30.));
+        assert!(too_steep.y < 30., "{too_steep}");
+
+        let unset = climb(20., None);
+        assert!(unset.y < 30., "{unset}");
+    }
+
+    #[test]
+    fn layers_filter_statics_and_triggers() {
+        let mut world = World::new();
+        let barrier_layers = CollisionLayers::new(0b10, 0b10);
+
+        // Stretched along z so both are in the way of both movers
+        let across = CollisionShape::Box {
+            half_width: 10.,
+            half_height: 10.,
+            half_depth: 200.,
+        };
+
+        let wall = spawn_static(&mut world, glam::vec3(50., 0., 0.), across.clone());
+        world.insert_one(wall, barrier_layers).unwrap();
+
+        let trigger = world.spawn((
+            Transform::from_translation(glam::vec3(20., 0., 0.)),
+            TriggerCollisionType,
+            across,
+            barrier_layers,
+        ));
+
+        let ignored = spawn_mover(&mut world, glam::Vec3::ZERO, glam::vec3(600., 0., 0.));
+        let blocked = spawn_mover(
+            &mut world,
+            glam::vec3(0., 0., 100.),
+            glam::vec3(600., 0., 0.),
+        );
+        world
+            .insert_one(ignored, CollisionLayers::new(0b01, u32::MAX))
+            .unwrap();
+        world.insert_one(blocked, barrier_layers).unwrap();
+
+        let mut physics = PhysicsHandler::default();
+        let mut triggered = HashSet::new();
+
+        (0..30).for_each(|_| {
+            physics.tick(&mut world, TICK);
+            physics.drain_trigger_events().for_each(|event| {
+                if let TriggerEvent::Entered {
+                    trigger: hit,
+                    other,
+                } = event
+                {
+                    assert_eq!(hit, trigger);
+                    triggered.insert(other);
+                }
+            });
+        });
+
+        assert!(translation(&world, ignored).x > 100.);
+        assert!(translation(&world, blocked).x < 35.1);
+        assert_eq!(triggered, HashSet::from([blocked]));
+    }
+
+    #[inline]
+    fn vertical_speed(world: &World, entity: Entity) -> f32 {
+        world.get::<&Velocity>(entity).unwrap().0.y
+    }
+
+    // Whether jump pressed a number of ticks after the floor disappears still jumps
+    fn jump_after_falling(ticks: u32) -> bool {
+        let mut world = World::new();
+        let floor = spawn_floor(&mut world);
+        let character = spawn_character(&mut world, glam::vec3(0., 21., 0.));
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 30);
+        assert!(world.get::<&Grounded>(character).is_ok());
+
+        world.despawn(floor).unwrap();
+        run(&mut physics, &mut world, ticks);
+
+        push_action(&world, character, MovementAction::Jump);
+        physics.tick(&mut world, TICK);
+
+        vertical_speed(&world, character) > 0.
+    }
+
+    #[test]
+    fn coyote_time() {
+        assert!(jump_after_falling(0));
+        assert!(jump_after_falling(3));
+        assert!(!jump_after_falling(12));
+    }
+
+    // Whether jump pressed a number of ticks before landing jumps once it lands
+    fn jump_before_landing(early: u32) -> bool {
+        let drop = |world: &mut World| {
+            spawn_floor(world);
+            spawn_character(world, glam::vec3(0., 80., 0.))
+        };
+
+        let mut world = World::new();
+        let character = drop(&mut world);
+        let mut physics = PhysicsHandler::default();
+
+        let landing = (1..120)
+            .find(|_| {
+                physics.tick(&mut world, TICK);
+                world.get::<&Grounded>(character).is_ok()
+            })
+            .unwrap();
+
+        let mut world = World::new();
+        let character = drop(&mut world);
+        let mut physics = PhysicsHandler::default();
+
+        run(&mut physics, &mut world, landing - early);
+        push_action(&world, character, MovementAction::Jump);
+
+        (0..early + 5).any(|_| {
+            physics.tick(&mut world, TICK);
+            vertical_speed(&world, character) > 0.
+        })
+    }
+
+    #[test]
+    fn jump_buffer() {
+        assert!(jump_before_landing(3));
+        assert!(!jump_before_landing(15));
+    }
+
+    // Character standing on the floor with jump just pressed
+    fn start_jump(world: &mut World, physics: &mut PhysicsHandler) -> Entity {
+        spawn_floor(world);
+        let character = spawn_character(world, glam::vec3(0., 21., 0.));
+        run(physics, world, 30);
+
+        push_action(world, character, MovementAction::Jump);
+        physics.tick(world, TICK);
+        assert!(vertical_speed(world, character) > 0.);
+
+        character
+    }
+
+    fn jump_peak(release_after: Option<u32>) -> f32 {
+        let mut world = World::new();
+        let mut physics = PhysicsHandler::default();
+        let character = start_jump(&mut world, &mut physics);
+
+        (1..90)
+            .map(|tick| {
+                if Some(tick) == release_after {
+                    push_action(&world, character, MovementAction::JumpRelease);
+                }
+
+                physics.tick(&mut world, TICK);
+                translation(&world, character).y
+            })
+            .fold(f32::MIN, f32::max)
+    }
+
+    #[test]
+    fn releasing_jump_early_cuts_it_short() {
+        let held = jump_peak(None);
+        let released = jump_peak(Some(3));
+
+        assert!(held > 60., "{held}");
+        assert!(released < held * 0.8, "{released} {held}");
+
+        // Releasing on the way down doesn't change anything
+        assert_eq!(jump_peak(Some(80)), held);
+    }
+
+    // How many extra jumps a character manages with jump pressed three times in the air
+    fn air_jumps_taken(air_jumps: Option<u32>) -> usize {
+        let mut world = World::new();
+        let mut physics = PhysicsHandler::default();
+        let character = start_jump(&mut world, &mut physics);
+
+        if let Some(air_jumps) = air_jumps {
+            world.insert_one(character, AirJumps(air_jumps)).unwrap();
+        }
+
+        (0..3)
+            .filter(|_| {
+                run(&mut physics, &mut world, 10);
+                let before = vertical_speed(&world, character);
+
+                push_action(&world, character, MovementAction::Jump);
+                physics.tick(&mut world, TICK);
+
+                vertical_speed(&world, character) > before
+            })
+            .count()
+    }
+
+    #[test]
+    fn air_jumps() {
+        assert_eq!(air_jumps_taken(None), 0);
+        assert_eq!(air_jumps_taken(Some(1)), 1);
+        assert_eq!(air_jumps_taken(Some(2)), 2);
+    }
+
+    fn spawn_surface(world: &mut World, material: PhysicsMaterial) {
+        let floor = spawn_floor(world);
+        world.insert_one(floor, material).unwrap();
+    }
+
+    // How far a character slides after letting go of move at speed
+    fn slide_distance(material: PhysicsMaterial) -> f32 {
+        let mut world = World::new();
+        spawn_surface(&mut world, material);
+        let character = spawn_character(&mut world, glam::vec3(0., 21., 0.));
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 30);
+
+        (0..30).for_each(|_| {
+            push_action(&world, character, MovementAction::Move((1., 0.)));
+            physics.tick(&mut world, TICK);
+        });
+
+        let released = translation(&world, character).x;
+        run(&mut physics, &mut world, 90);
+
+        translation(&world, character).x - released
+    }
+
+    #[test]
+    fn surface_friction() {
+        let normal = slide_distance(PhysicsMaterial::default());
+        let ice = slide_distance(PhysicsMaterial::ICE);
+
+        assert!(normal > 0., "{normal}");
+        assert!(ice > normal * 1.5, "{ice} {normal}");
+    }
+
+    // Highest upward speed a mover dropped onto the surface gets
+    fn rebound_speed(material: PhysicsMaterial) -> f32 {
+        let mut world = World::new();
+        spawn_surface(&mut world, material);
+        let mover = spawn_mover(&mut world, glam::vec3(0., 100., 0.), glam::Vec3::ZERO);
+        world
+            .insert_one(mover, Gravity(glam::vec3(0., -400., 0.)))
+            .unwrap();
+
+        let mut physics = PhysicsHandler::default();
+
+        (0..90)
+            .map(|_| {
+                physics.tick(&mut world, TICK);
+                vertical_speed(&world, mover)
+            })
+            .fold(f32::MIN, f32::max)
+    }
+
+    #[test]
+    fn surface_bounce() {
+        assert!(rebound_speed(PhysicsMaterial::default()) <= 0.);
+        assert!(rebound_speed(PhysicsMaterial::BOUNCY) > 200.);
+    }
+
+    #[test]
+    fn surface_conveyor() {
+        let mut world = World::new();
+        spawn_surface(
+            &mut world,
+            PhysicsMaterial::conveyor(glam::vec3(100., 0., 0.)),
+        );
+        let character = spawn_character(&mut world, glam::vec3(0., 21., 0.));
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 60);
+
+        assert!(translation(&world, character).x > 50.);
+    }
+
+    #[test]
+    fn crate_comes_to_rest_on_floor() {
+        let mut world = World::new();
+        spawn_floor(&mut world);
+
+        let crate_entity = world.spawn(RigidBodyBundle::new(cube(10.), 1.));
+        world
+            .insert_one(
+                crate_entity,
+                Transform::from_translation(glam::vec3(0., 60., 0.)),
+            )
+            .unwrap();
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 180);
+
+        let transform = world.get::<&Transform>(crate_entity).unwrap();
+        let velocity = world.get::<&Velocity>(crate_entity).unwrap().0;
+
+        assert!(
+            (transform.translation.y - 10.).abs() < 0.5,
+            "{}",
+            transform.translation
+        );
+        assert!(transform.rotation.angle_between(glam::Quat::IDENTITY) < 0.01);
+        assert!(velocity.length() < 1., "{velocity}");
+    }
+
+    #[test]
+    fn character_pushes_crate_without_passing_through() {
+        let mut world = World::new();
+        spawn_floor(&mut world);
+
+        let character = spawn_character(&mut world, glam::vec3(0., 21., 0.));
+        let crate_entity = world.spawn(RigidBodyBundle::new(cube(10.), 1.));
+        world
+            .insert_one(
+                crate_entity,
+                Transform::from_translation(glam::vec3(60., 10., 0.)),
+            )
+            .unwrap();
+
+        let mut physics = PhysicsHandler::default();
+
+        (0..90).for_each(|_| {
+            push_action(&world, character, MovementAction::Move((1., 0.)));
+            physics.tick(&mut world, TICK);
+
+            let gap = translation(&world, crate_entity).x - translation(&world, character).x;
+            assert!(gap > 29., "{gap}");
+        });
+
+        assert!(translation(&world, character).x > 50.);
+        assert!(translation(&world, crate_entity).x > 80.);
+    }
+}
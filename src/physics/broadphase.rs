@@ -0,0 +1,317 @@
+//====================================================================
+
+use std::collections::HashMap;
+
+use hecs::Entity;
+
+//====================================================================
+
+const DEFAULT_CELL_SIZE: f32 = 64.;
+
+// Anything spanning more cells than this is kept out of the grid and always
+// returned as a candidate - stops huge floors from filling thousands of cells.
+const MAX_CELLS_PER_ENTRY: i32 = 4096;
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+}
+
+impl Aabb {
+    #[inline]
+    pub fn new(min: glam::Vec3, max: glam::Vec3) -> Self {
+        Self { min, max }
+    }
+
+    #[inline]
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    #[inline]
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    // Span of travel (0..1) where this box overlaps other while moving by displacement
+    pub fn overlap_window(&self, displacement: glam::Vec3, other: &Aabb) -> Option<(f32, f32)> {
+        let mut enter = 0_f32;
+        let mut exit = 1_f32;
+
+        for axis in 0..3 {
+            let distance = displacement[axis];
+
+            match distance == 0. {
+                true => {
+                    if self.max[axis] < other.min[axis] || self.min[axis] > other.max[axis] {
+                        return None;
+                    }
+                }
+                false => {
+                    let a = (other.min[axis] - self.max[axis]) / distance;
+                    let b = (other.max[axis] - self.min[axis]) / distance;
+
+                    enter = enter.max(a.min(b));
+                    exit = exit.min(a.max(b));
+                }
+            }
+        }
+
+        match enter <= exit {
+            true => Some((enter, exit)),
+            false => None,
+        }
+    }
+}
+
+//====================================================================
+
+#[derive(Clone, Copy)]
+struct CellRange {
+    min: glam::IVec3,
+    max: glam::IVec3,
+}
+
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: HashMap<glam::IVec3, Vec<Entity>>,
+    entries: HashMap<Entity, Option<CellRange>>,
+    oversized: Vec<Entity>,
+
+    // Every cell anything has been put in. Never shrunk, it's only for knowing
+    // when a ray has passed everything.
+    occupied: Option<CellRange>,
+}
+
+impl Default for SpatialGrid {
+    #[inline]
+    fn default() -> Self {
+        Self::new(DEFAULT_CELL_SIZE)
+    }
+}
+
+impl SpatialGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::default(),
+            entries: HashMap::default(),
+            oversized: Vec::new(),
+            occupied: None,
+        }
+    }
+
+    #[inline]
+    pub fn entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.entries.keys().copied()
+    }
+
+    pub fn insert(&mut self, entity: Entity, bounds: Aabb) {
+        self.remove(entity);
+
+        let range = self.cell_range(&bounds);
+        let size = range.max - range.min + glam::IVec3::ONE;
+
+        if size.x as i64 * size.y as i64 * size.z as i64 > MAX_CELLS_PER_ENTRY as i64 {
+            self.oversized.push(entity);
+            self.entries.insert(entity, None);
+            return;
+        }
+
+        for_each_cell(range, |cell| {
+            self.cells.entry(cell).or_default().push(entity);
+        });
+
+        self.occupied = Some(match self.occupied {
+            Some(occupied) => CellRange {
+                min: occupied.min.min(range.min),
+                max: occupied.max.max(range.max),
+            },
+            None => range,
+        });
+
+        self.entries.insert(entity, Some(range));
+    }
+
+    pub fn remove(&mut self, entity: Entity) {
+        let range = match self.entries.remove(&entity) {
+            Some(Some(range)) => range,
+            Some(None) => {
+                self.oversized.retain(|oversized| *oversized != entity);
+                return;
+            }
+            None => return,
+        };
+
+        for_each_cell(range, |cell| {
+            if let Some(entities) = self.cells.get_mut(&cell) {
+                entities.retain(|cell_entity| *cell_entity != entity);
+
+                if entities.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        });
+    }
+
+    pub fn query(&self, bounds: &Aabb) -> Vec<Entity> {
+        let mut candidates = self.oversized.clone();
+
+        for_each_cell(self.cell_range(bounds), |cell| {
+            if let Some(entities) = self.cells.get(&cell) {
+                candidates.extend_from_slice(entities);
+            }
+        });
+
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        candidates
+    }
+
+    // Walks the cells along a ray rather than querying its bounds, which would
+    // cover a huge number of cells for long diagonal rays.
+    pub fn query_ray(
+        &self,
+        origin: glam::Vec3,
+        direction: glam::Vec3,
+        max_distance: f32,
+    ) -> Vec<Entity> {
+        let mut candidates = self.oversized.clone();
+
+        let occupied = match self.occupied {
+            Some(occupied) => occupied,
+            None => return candidates,
+        };
+
+        let mut cell = (origin / self.cell_size).floor().as_ivec3();
+        let mut next_boundary = glam::Vec3::splat(f32::INFINITY);
+        let mut boundary_spacing = glam::Vec3::splat(f32::INFINITY);
+        let mut step = glam::IVec3::ZERO;
+
+        for axis in 0..3 {
+            if direction[axis] == 0. {
+                continue;
+            }
+
+            step[axis] = direction[axis].signum() as i32;
+
+            let boundary = match direction[axis] > 0. {
+                true => (cell[axis] + 1) as f32 * self.cell_size,
+                false => cell[axis] as f32 * self.cell_size,
+            };
+
+            next_boundary[axis] = (boundary - origin[axis]) / direction[axis];
+            boundary_spacing[axis] = self.cell_size / direction[axis].abs();
+        }
+
+        loop {
+            if let Some(entities) = self.cells.get(&cell) {
+                candidates.extend_from_slice(entities);
+            }
+
+            let axis = match (
+                next_boundary.x <= next_boundary.y && next_boundary.x <= next_boundary.z,
+                next_boundary.y <= next_boundary.z,
+            ) {
+                (true, _) => 0,
+                (false, true) => 1,
+                (false, false) => 2,
+            };
+
+            // Long or infinite rays stop once they're past every occupied cell
+            if !next_boundary[axis].is_finite() || next_boundary[axis] > max_distance {
+                break;
+            }
+
+            cell[axis] += step[axis];
+            next_boundary[axis] += boundary_spacing[axis];
+
+            if (step[axis] > 0 && cell[axis] > occupied.max[axis])
+                || (step[axis] < 0 && cell[axis] < occupied.min[axis])
+            {
+                break;
+            }
+        }
+
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        candidates
+    }
+
+    #[inline]
+    fn cell_range(&self, bounds: &Aabb) -> CellRange {
+        CellRange {
+            min: (bounds.min / self.cell_size).floor().as_ivec3(),
+            max: (bounds.max / self.cell_size).floor().as_ivec3(),
+        }
+    }
+}
+
+#[inline]
+fn for_each_cell(range: CellRange, mut f: impl FnMut(glam::IVec3)) {
+    for x in range.min.x..=range.max.x {
+        for y in range.min.y..=range.max.y {
+            for z in range.min.z..=range.max.z {
+                f(glam::ivec3(x, y, z));
+            }
+        }
+    }
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_ray_without_max_distance() {
+        let mut grid = SpatialGrid::default();
+        let entity = Entity::from_bits(1).unwrap();
+
+        grid.insert(
+            entity,
+            Aabb::new(glam::vec3(300., 0., 0.), glam::vec3(310., 10., 10.)),
+        );
+
+        let origin = glam::vec3(0., 5., 5.);
+
+        assert_eq!(
+            grid.query_ray(origin, glam::Vec3::X, f32::INFINITY),
+            vec![entity]
+        );
+        assert!(grid
+            .query_ray(origin, -glam::Vec3::X, f32::INFINITY)
+            .is_empty());
+        assert!(grid
+            .query_ray(origin, glam::Vec3::ZERO, f32::INFINITY)
+            .is_empty());
+
+        // Only needs to come back at all
+        grid.query_ray(origin, glam::Vec3::X, f32::NAN);
+    }
+
+    #[test]
+    fn overlap_window() {
+        let moving = Aabb::new(glam::Vec3::ZERO, glam::Vec3::splat(10.));
+        let other = Aabb::new(glam::vec3(50., 0., 0.), glam::vec3(60., 10., 10.));
+
+        assert_eq!(
+            moving.overlap_window(glam::vec3(100., 0., 0.), &other),
+            Some((0.4, 0.6))
+        );
+        assert_eq!(moving.overlap_window(glam::vec3(30., 0., 0.), &other), None);
+        assert_eq!(
+            moving.overlap_window(glam::vec3(100., 50., 0.), &other),
+            None
+        );
+    }
+}
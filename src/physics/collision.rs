@@ -0,0 +1,1344 @@
+//====================================================================
+
+use super::{broadphase::Aabb, CollisionShape};
+pub use heightfield::Heightfield;
+use trimesh::PreppedTriMesh;
+pub use trimesh::TriMesh;
+
+mod heightfield;
+mod trimesh;
+
+//====================================================================
+
+const SEGMENT_SEARCH_STEPS: u32 = 24;
+const AXIS_ALIGNED_EPSILON: f32 = 0.00001;
+
+const CONTACT_AXIS_EPSILON: f32 = 0.0001;
+const CONTACT_TOLERANCE: f32 = 0.05;
+const EDGE_AXIS_BIAS: f32 = 1.05;
+
+const WIREFRAME_CIRCLE_SEGMENTS: u32 = 16;
+
+const SWEEP_STEPS: u32 = 12;
+
+pub(super) enum PreppedCollisionShape {
+    Box((Range, Range, Range)),
+    Obb(Obb),
+    Sphere {
+        center: glam::Vec3,
+        radius: f32,
+    },
+    Capsule {
+        start: glam::Vec3,
+        end: glam::Vec3,
+        radius: f32,
+    },
+    TriMesh(PreppedTriMesh),
+    Compound(Vec<PreppedCollisionShape>),
+}
+
+pub(super) struct Range {
+    pub min: f32,
+    pub max: f32,
+}
+
+// Normal points out of the other shape, towards the shape the contact was generated for
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Contact {
+    pub point: glam::Vec3,
+    pub normal: glam::Vec3,
+    pub depth: f32,
+}
+
+impl Contact {
+    #[inline]
+    fn flip(self) -> Self {
+        Self {
+            normal: -self.normal,
+            ..self
+        }
+    }
+}
+
+pub(super) struct Obb {
+    pub center: glam::Vec3,
+    pub half_extents: glam::Vec3,
+    pub axes: [glam::Vec3; 3],
+}
+
+impl Obb {
+    #[inline]
+    fn from_aabb(aabb: &(Range, Range, Range)) -> Self {
+        let min = glam::vec3(aabb.0.min, aabb.1.min, aabb.2.min);
+        let max = glam::vec3(aabb.0.max, aabb.1.max, aabb.2.max);
+
+        Self {
+            center: (min + max) * 0.5,
+            half_extents: (max - min) * 0.5,
+            axes: [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z],
+        }
+    }
+
+    #[inline]
+    fn to_local(&self, point: glam::Vec3) -> glam::Vec3 {
+        let offset = point - self.center;
+        glam::vec3(
+            offset.dot(self.axes[0]),
+            offset.dot(self.axes[1]),
+            offset.dot(self.axes[2]),
+        )
+    }
+
+    #[inline]
+    fn to_world(&self, point: glam::Vec3) -> glam::Vec3 {
+        self.center + self.direction_to_world(point)
+    }
+
+    #[inline]
+    fn direction_to_world(&self, direction: glam::Vec3) -> glam::Vec3 {
+        self.axes[0] * direction.x + self.axes[1] * direction.y + self.axes[2] * direction.z
+    }
+
+    fn vertices(&self) -> [glam::Vec3; 8] {
+        let mut vertices = [glam::Vec3::ZERO; 8];
+
+        vertices.iter_mut().enumerate().for_each(|(index, vertex)| {
+            let corner = glam::vec3(
+                if index & 1 == 0 { -1. } else { 1. },
+                if index & 2 == 0 { -1. } else { 1. },
+                if index & 4 == 0 { -1. } else { 1. },
+            );
+
+            *vertex = self.to_world(corner * self.half_extents);
+        });
+
+        vertices
+    }
+
+    fn wireframe(&self) -> Vec<(glam::Vec3, glam::Vec3)> {
+        let vertices = self.vertices();
+
+        // Vertex index bits are the x, y and z sides, so edges join indices one bit apart
+        (0..8_usize)
+            .flat_map(|index| {
+                [1, 2, 4]
+                    .into_iter()
+                    .filter(move |bit| index & bit == 0)
+                    .map(move |bit| (vertices[index], vertices[index | bit]))
+            })
+            .collect()
+    }
+
+    #[inline]
+    fn contains(&self, point: glam::Vec3, tolerance: f32) -> bool {
+        self.to_local(point)
+            .abs()
+            .cmple(self.half_extents + glam::Vec3::splat(tolerance))
+            .all()
+    }
+
+    #[inline]
+    fn local_aabb(&self) -> (Range, Range, Range) {
+        aabb_from_center(glam::Vec3::ZERO, self.half_extents)
+    }
+
+    #[inline]
+    fn world_half_extents(&self) -> glam::Vec3 {
+        self.axes[0].abs() * self.half_extents.x
+            + self.axes[1].abs() * self.half_extents.y
+            + self.axes[2].abs() * self.half_extents.z
+    }
+}
+
+impl PreppedCollisionShape {
+    pub fn from_collision_shape(
+        value: &CollisionShape,
+        translation: glam::Vec3,
+        rotation: glam::Quat,
+        scale: glam::Vec3,
+    ) -> Self {
+        match value {
+            CollisionShape::Box {
+                half_width,
+                half_height,
+                half_depth,
+            } => {
+                let half_extents = glam::vec3(*half_width, *half_height, *half_depth) * scale;
+                let rotation = glam::Mat3::from_quat(rotation);
+
+                // Keep the cheap AABB path for anything not rotated off the world axes
+                match is_axis_aligned(&rotation) {
+                    true => PreppedCollisionShape::Box(aabb_from_center(
+                        translation,
+                        rotation.abs() * half_extents,
+                    )),
+                    false => PreppedCollisionShape::Obb(Obb {
+                        center: translation,
+                        half_extents,
+                        axes: [rotation.x_axis, rotation.y_axis, rotation.z_axis],
+                    }),
+                }
+            }
+
+            CollisionShape::Sphere { radius } => PreppedCollisionShape::Sphere {
+                center: translation,
+                radius: radius * scale.max_element(),
+            },
+
+            CollisionShape::Capsule {
+                radius,
+                half_height,
+            } => {
+                let radius = radius * scale.x.max(scale.z);
+                let half_segment = (half_height * scale.y - radius).max(0.);
+
+                let axis = rotation * glam::Vec3::Y;
+
+                PreppedCollisionShape::Capsule {
+                    start: translation - axis * half_segment,
+                    end: translation + axis * half_segment,
+                    radius,
+                }
+            }
+
+            CollisionShape::TriMesh(mesh) => PreppedCollisionShape::TriMesh(PreppedTriMesh::new(
+                mesh,
+                translation,
+                rotation,
+                scale,
+            )),
+
+            CollisionShape::Heightfield(heightfield) => PreppedCollisionShape::TriMesh(
+                PreppedTriMesh::new(heightfield.mesh(), translation, rotation, scale),
+            ),
+
+            // Offsets are moved along with the entity's own rotation and scale
+            CollisionShape::Compound(parts) => PreppedCollisionShape::Compound(
+                parts
+                    .iter()
+                    .map(|(offset, shape)| {
+                        Self::from_collision_shape(
+                            shape,
+                            translation + rotation * (*offset * scale),
+                            rotation,
+                            scale,
+                        )
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        match self {
+            PreppedCollisionShape::Box((x, y, z)) => Aabb::new(
+                glam::vec3(x.min, y.min, z.min),
+                glam::vec3(x.max, y.max, z.max),
+            ),
+
+            PreppedCollisionShape::Obb(obb) => {
+                let half_extents = obb.world_half_extents();
+                Aabb::new(obb.center - half_extents, obb.center + half_extents)
+            }
+
+            PreppedCollisionShape::Sphere { center, radius } => Aabb::new(
+                *center - glam::Vec3::splat(*radius),
+                *center + glam::Vec3::splat(*radius),
+            ),
+
+            PreppedCollisionShape::Capsule { start, end, radius } => Aabb::new(
+                start.min(*end) - glam::Vec3::splat(*radius),
+                start.max(*end) + glam::Vec3::splat(*radius),
+            ),
+
+            PreppedCollisionShape::TriMesh(mesh) => mesh.bounds(),
+
+            PreppedCollisionShape::Compound(parts) => parts
+                .iter()
+                .map(|part| part.bounds())
+                .reduce(|a, b| a.union(&b))
+                .unwrap_or(Aabb::new(glam::Vec3::ZERO, glam::Vec3::ZERO)),
+        }
+    }
+
+    pub fn check_collision(&self, other: &PreppedCollisionShape) -> bool {
+        match (self, other) {
+            (PreppedCollisionShape::Compound(parts), other)
+            | (other, PreppedCollisionShape::Compound(parts)) => {
+                parts.iter().any(|part| part.check_collision(other))
+            }
+
+            (PreppedCollisionShape::TriMesh(mesh), other)
+            | (other, PreppedCollisionShape::TriMesh(mesh)) => mesh.check_collision(other),
+
+            (PreppedCollisionShape::Box(a), PreppedCollisionShape::Box(b)) => aabb_vs_aabb(a, b),
+
+            (PreppedCollisionShape::Obb(a), PreppedCollisionShape::Obb(b)) => obb_vs_obb(a, b),
+
+            (PreppedCollisionShape::Box(aabb), PreppedCollisionShape::Obb(obb))
+            | (PreppedCollisionShape::Obb(obb), PreppedCollisionShape::Box(aabb)) => {
+                obb_vs_obb(&Obb::from_aabb(aabb), obb)
+            }
+
+            (PreppedCollisionShape::Obb(obb), PreppedCollisionShape::Sphere { center, radius })
+            | (PreppedCollisionShape::Sphere { center, radius }, PreppedCollisionShape::Obb(obb)) => {
+                aabb_vs_sphere(&obb.local_aabb(), obb.to_local(*center), *radius)
+            }
+
+            (
+                PreppedCollisionShape::Obb(obb),
+                PreppedCollisionShape::Capsule { start, end, radius },
+            )
+            | (
+                PreppedCollisionShape::Capsule { start, end, radius },
+                PreppedCollisionShape::Obb(obb),
+            ) => aabb_vs_capsule(
+                &obb.local_aabb(),
+                obb.to_local(*start),
+                obb.to_local(*end),
+                *radius,
+            ),
+
+            (
+                PreppedCollisionShape::Box(aabb),
+                PreppedCollisionShape::Sphere { center, radius },
+            )
+            | (
+                PreppedCollisionShape::Sphere { center, radius },
+                PreppedCollisionShape::Box(aabb),
+            ) => aabb_vs_sphere(aabb, *center, *radius),
+
+            (
+                PreppedCollisionShape::Box(aabb),
+                PreppedCollisionShape::Capsule { start, end, radius },
+            )
+            | (
+                PreppedCollisionShape::Capsule { start, end, radius },
+                PreppedCollisionShape::Box(aabb),
+            ) => aabb_vs_capsule(aabb, *start, *end, *radius),
+
+            (
+                PreppedCollisionShape::Sphere {
+                    center: a,
+                    radius: a_radius,
+                },
+                PreppedCollisionShape::Sphere {
+                    center: b,
+                    radius: b_radius,
+                },
+            ) => a.distance_squared(*b) <= (a_radius + b_radius).powi(2),
+
+            (
+                PreppedCollisionShape::Sphere {
+                    center,
+                    radius: sphere_radius,
+                },
+                PreppedCollisionShape::Capsule {
+                    start,
+                    end,
+                    radius: capsule_radius,
+                },
+            )
+            | (
+                PreppedCollisionShape::Capsule {
+                    start,
+                    end,
+                    radius: capsule_radius,
+                },
+                PreppedCollisionShape::Sphere {
+                    center,
+                    radius: sphere_radius,
+                },
+            ) => {
+                let closest = closest_point_on_segment(*start, *end, *center);
+                closest.distance_squared(*center) <= (sphere_radius + capsule_radius).powi(2)
+            }
+
+            (
+                PreppedCollisionShape::Capsule {
+                    start: a_start,
+                    end: a_end,
+                    radius: a_radius,
+                },
+                PreppedCollisionShape::Capsule {
+                    start: b_start,
+                    end: b_end,
+                    radius: b_radius,
+                },
+            ) => {
+                let (a, b) = closest_points_between_segments(*a_start, *a_end, *b_start, *b_end);
+                a.distance_squared(b) <= (a_radius + b_radius).powi(2)
+            }
+        }
+    }
+}
+
+impl PreppedCollisionShape {
+    pub fn contact(&self, other: &PreppedCollisionShape) -> Option<Contact> {
+        match (self, other) {
+            // Deepest contact of any part, which is the one that needs resolving first
+            (PreppedCollisionShape::Compound(parts), other) => parts
+                .iter()
+                .filter_map(|part| part.contact(other))
+                .max_by(|a, b| a.depth.total_cmp(&b.depth)),
+
+            (other, PreppedCollisionShape::Compound(parts)) => parts
+                .iter()
+                .filter_map(|part| other.contact(part))
+                .max_by(|a, b| a.depth.total_cmp(&b.depth)),
+
+            (PreppedCollisionShape::TriMesh(mesh), other) => mesh.contact(other).map(Contact::flip),
+            (other, PreppedCollisionShape::TriMesh(mesh)) => mesh.contact(other),
+
+            (PreppedCollisionShape::Box(a), PreppedCollisionShape::Box(b)) => aabb_contact(a, b),
+
+            (PreppedCollisionShape::Obb(a), PreppedCollisionShape::Obb(b)) => obb_contact(a, b),
+
+            (PreppedCollisionShape::Box(aabb), PreppedCollisionShape::Obb(obb)) => {
+                obb_contact(&Obb::from_aabb(aabb), obb)
+            }
+
+            (PreppedCollisionShape::Obb(obb), PreppedCollisionShape::Box(aabb)) => {
+                obb_contact(obb, &Obb::from_aabb(aabb))
+            }
+
+            (
+                PreppedCollisionShape::Box(aabb),
+                PreppedCollisionShape::Sphere { center, radius },
+            ) => aabb_round_contact(aabb, *center, *radius).map(Contact::flip),
+
+            (
+                PreppedCollisionShape::Sphere { center, radius },
+                PreppedCollisionShape::Box(aabb),
+            ) => aabb_round_contact(aabb, *center, *radius),
+
+            (
+                PreppedCollisionShape::Box(aabb),
+                PreppedCollisionShape::Capsule { start, end, radius },
+            ) => {
+                let point = closest_segment_point_to_aabb(aabb, *start, *end);
+                aabb_round_contact(aabb, point, *radius).map(Contact::flip)
+            }
+
+            (
+                PreppedCollisionShape::Capsule { start, end, radius },
+                PreppedCollisionShape::Box(aabb),
+            ) => {
+                let point = closest_segment_point_to_aabb(aabb, *start, *end);
+                aabb_round_contact(aabb, point, *radius)
+            }
+
+            (PreppedCollisionShape::Obb(obb), PreppedCollisionShape::Sphere { center, radius }) => {
+                obb_round_contact(obb, *center, *radius).map(Contact::flip)
+            }
+
+            (PreppedCollisionShape::Sphere { center, radius }, PreppedCollisionShape::Obb(obb)) => {
+                obb_round_contact(obb, *center, *radius)
+            }
+
+            (
+                PreppedCollisionShape::Obb(obb),
+                PreppedCollisionShape::Capsule { start, end, radius },
+            ) => {
+                let point = obb_closest_segment_point(obb, *start, *end);
+                obb_round_contact(obb, point, *radius).map(Contact::flip)
+            }
+
+            (
+                PreppedCollisionShape::Capsule { start, end, radius },
+                PreppedCollisionShape::Obb(obb),
+            ) => {
+                let point = obb_closest_segment_point(obb, *start, *end);
+                obb_round_contact(obb, point, *radius)
+            }
+
+            (
+                PreppedCollisionShape::Sphere {
+                    center: a,
+                    radius: a_radius,
+                },
+                PreppedCollisionShape::Sphere {
+                    center: b,
+                    radius: b_radius,
+                },
+            ) => round_contact(*a, *a_radius, *b, *b_radius),
+
+            (
+                PreppedCollisionShape::Sphere {
+                    center,
+                    radius: sphere_radius,
+                },
+                PreppedCollisionShape::Capsule { start, end, radius },
+            ) => {
+                let closest = closest_point_on_segment(*start, *end, *center);
+                round_contact(*center, *sphere_radius, closest, *radius)
+            }
+
+            (
+                PreppedCollisionShape::Capsule { start, end, radius },
+                PreppedCollisionShape::Sphere {
+                    center,
+                    radius: sphere_radius,
+                },
+            ) => {
+                let closest = closest_point_on_segment(*start, *end, *center);
+                round_contact(closest, *radius, *center, *sphere_radius)
+            }
+
+            (
+                PreppedCollisionShape::Capsule {
+                    start: a_start,
+                    end: a_end,
+                    radius: a_radius,
+                },
+                PreppedCollisionShape::Capsule {
+                    start: b_start,
+                    end: b_end,
+                    radius: b_radius,
+                },
+            ) => {
+                let (a, b) = closest_points_between_segments(*a_start, *a_end, *b_start, *b_end);
+                round_contact(a, *a_radius, b, *b_radius)
+            }
+        }
+    }
+}
+
+impl PreppedCollisionShape {
+    // Line segments outlining the shape, for debug drawing
+    pub fn wireframe(&self) -> Vec<(glam::Vec3, glam::Vec3)> {
+        match self {
+            PreppedCollisionShape::Box(aabb) => Obb::from_aabb(aabb).wireframe(),
+            PreppedCollisionShape::Obb(obb) => obb.wireframe(),
+
+            PreppedCollisionShape::Sphere { center, radius } => {
+                [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z]
+                    .into_iter()
+                    .flat_map(|axis| circle_edges(*center, axis, *radius))
+                    .collect()
+            }
+
+            PreppedCollisionShape::Capsule { start, end, radius } => {
+                let axis = (*end - *start).try_normalize().unwrap_or(glam::Vec3::Y);
+                let (side, forward) = axis.any_orthonormal_pair();
+
+                let mut edges = [*start, *end]
+                    .into_iter()
+                    .flat_map(|center| {
+                        [axis, side, forward]
+                            .into_iter()
+                            .flat_map(move |normal| circle_edges(center, normal, *radius))
+                    })
+                    .collect::<Vec<_>>();
+
+                edges.extend(
+                    [side, -side, forward, -forward]
+                        .into_iter()
+                        .map(|offset| (*start + offset * *radius, *end + offset * *radius)),
+                );
+
+                edges
+            }
+
+            PreppedCollisionShape::TriMesh(mesh) => mesh.wireframe(),
+
+            PreppedCollisionShape::Compound(parts) => {
+                parts.iter().flat_map(|part| part.wireframe()).collect()
+            }
+        }
+    }
+}
+
+impl PreppedCollisionShape {
+    // Distance along a normalized direction to where a ray enters the shape, and
+    // the surface normal there. Rays starting inside a shape hit it straight away.
+    pub fn raycast(
+        &self,
+        origin: glam::Vec3,
+        direction: glam::Vec3,
+        max_distance: f32,
+    ) -> Option<(f32, glam::Vec3)> {
+        match self {
+            PreppedCollisionShape::Box((x, y, z)) => ray_box(
+                glam::vec3(x.min, y.min, z.min),
+                glam::vec3(x.max, y.max, z.max),
+                origin,
+                direction,
+                max_distance,
+            ),
+
+            PreppedCollisionShape::Obb(obb) => {
+                let local_direction = glam::vec3(
+                    direction.dot(obb.axes[0]),
+                    direction.dot(obb.axes[1]),
+                    direction.dot(obb.axes[2]),
+                );
+
+                ray_box(
+                    -obb.half_extents,
+                    obb.half_extents,
+                    obb.to_local(origin),
+                    local_direction,
+                    max_distance,
+                )
+                .map(|(distance, normal)| (distance, obb.direction_to_world(normal)))
+            }
+
+            PreppedCollisionShape::Sphere { center, radius } => {
+                ray_sphere(*center, *radius, origin, direction, max_distance)
+            }
+
+            PreppedCollisionShape::Capsule { start, end, radius } => {
+                ray_capsule(*start, *end, *radius, origin, direction, max_distance)
+            }
+
+            PreppedCollisionShape::TriMesh(mesh) => mesh.raycast(origin, direction, max_distance),
+
+            PreppedCollisionShape::Compound(parts) => parts
+                .iter()
+                .filter_map(|part| part.raycast(origin, direction, max_distance))
+                .min_by(|(a, _), (b, _)| a.total_cmp(b)),
+        }
+    }
+}
+
+impl PreppedCollisionShape {
+    // Time of impact (0..1) of a box moving by displacement into this shape, and the
+    // normal it hits. None if they don't meet or already overlap.
+    pub fn sweep(&self, moving: &Aabb, displacement: glam::Vec3) -> Option<(f32, glam::Vec3)> {
+        match self {
+            PreppedCollisionShape::Box(_) => swept_aabb(moving, displacement, &self.bounds()),
+
+            PreppedCollisionShape::TriMesh(mesh) => mesh.sweep(moving, displacement),
+
+            PreppedCollisionShape::Compound(parts) => parts
+                .iter()
+                .filter_map(|part| part.sweep(moving, displacement))
+                .min_by(|(a, _), (b, _)| a.total_cmp(b)),
+
+            // Rotated and rounded shapes don't fill their bounds, so test the real shape
+            // everywhere the moving box passes through them
+            _ => {
+                let center = (moving.min + moving.max) * 0.5;
+                let half_extents = (moving.max - moving.min) * 0.5;
+
+                let box_at = |time: f32| {
+                    PreppedCollisionShape::Box(aabb_from_center(
+                        center + displacement * time,
+                        half_extents,
+                    ))
+                };
+
+                if self.check_collision(&box_at(0.)) {
+                    return None;
+                }
+
+                let window = moving.overlap_window(displacement, &self.bounds())?;
+                let (free, blocked) = sample_sweep(moving, displacement, window, |time| {
+                    self.check_collision(&box_at(time))
+                })?;
+
+                box_at(blocked)
+                    .contact(self)
+                    .map(|contact| (free, contact.normal))
+            }
+        }
+    }
+}
+
+//====================================================================
+
+#[inline]
+fn aabb_vs_aabb(a: &(Range, Range, Range), b: &(Range, Range, Range)) -> bool {
+    a.0.min <= b.0.max
+        && a.0.max >= b.0.min
+        && a.1.min <= b.1.max
+        && a.1.max >= b.1.min
+        && a.2.min <= b.2.max
+        && a.2.max >= b.2.min
+}
+
+#[inline]
+fn aabb_vs_sphere(aabb: &(Range, Range, Range), center: glam::Vec3, radius: f32) -> bool {
+    closest_point_on_aabb(aabb, center).distance_squared(center) <= radius * radius
+}
+
+// Separating axis test between two oriented boxes - the 3 face axes of each box
+// plus the 9 edge cross products.
+fn obb_vs_obb(a: &Obb, b: &Obb) -> bool {
+    let a_extents = a.half_extents.to_array();
+    let b_extents = b.half_extents.to_array();
+
+    let mut rotation = [[0.; 3]; 3];
+    let mut abs_rotation = [[0.; 3]; 3];
+
+    for i in 0..3 {
+        for j in 0..3 {
+            rotation[i][j] = a.axes[i].dot(b.axes[j]);
+            // Epsilon stops near parallel edges producing a null cross product axis
+            abs_rotation[i][j] = rotation[i][j].abs() + AXIS_ALIGNED_EPSILON;
+        }
+    }
+
+    let translation = a.to_local(b.center).to_array();
+
+    for i in 0..3 {
+        let a_radius = a_extents[i];
+        let b_radius = b_extents[0] * abs_rotation[i][0]
+            + b_extents[1] * abs_rotation[i][1]
+            + b_extents[2] * abs_rotation[i][2];
+
+        if translation[i].abs() > a_radius + b_radius {
+            return false;
+        }
+    }
+
+    for j in 0..3 {
+        let a_radius = a_extents[0] * abs_rotation[0][j]
+            + a_extents[1] * abs_rotation[1][j]
+            + a_extents[2] * abs_rotation[2][j];
+        let b_radius = b_extents[j];
+
+        let distance = translation[0] * rotation[0][j]
+            + translation[1] * rotation[1][j]
+            + translation[2] * rotation[2][j];
+
+        if distance.abs() > a_radius + b_radius {
+            return false;
+        }
+    }
+
+    for i in 0..3 {
+        let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+
+        for j in 0..3 {
+            let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+
+            let a_radius =
+                a_extents[i1] * abs_rotation[i2][j] + a_extents[i2] * abs_rotation[i1][j];
+            let b_radius =
+                b_extents[j1] * abs_rotation[i][j2] + b_extents[j2] * abs_rotation[i][j1];
+
+            let distance = translation[i2] * rotation[i1][j] - translation[i1] * rotation[i2][j];
+
+            if distance.abs() > a_radius + b_radius {
+                return false;
+            }
+        }
+    }
+
+    true
+}
+
+fn aabb_vs_capsule(
+    aabb: &(Range, Range, Range),
+    start: glam::Vec3,
+    end: glam::Vec3,
+    radius: f32,
+) -> bool {
+    let point = closest_segment_point_to_aabb(aabb, start, end);
+    closest_point_on_aabb(aabb, point).distance_squared(point) <= radius * radius
+}
+
+//====================================================================
+
+fn aabb_contact(a: &(Range, Range, Range), b: &(Range, Range, Range)) -> Option<Contact> {
+    if !aabb_vs_aabb(a, b) {
+        return None;
+    }
+
+    let (a, b) = (Obb::from_aabb(a), Obb::from_aabb(b));
+
+    let overlap_min = (a.center - a.half_extents).max(b.center - b.half_extents);
+    let overlap_max = (a.center + a.half_extents).min(b.center + b.half_extents);
+    let overlap = overlap_max - overlap_min;
+
+    let axis = match (
+        overlap.x <= overlap.y && overlap.x <= overlap.z,
+        overlap.y <= overlap.z,
+    ) {
+        (true, _) => 0,
+        (false, true) => 1,
+        (false, false) => 2,
+    };
+
+    let mut normal = glam::Vec3::ZERO;
+    normal[axis] = match a.center[axis] >= b.center[axis] {
+        true => 1.,
+        false => -1.,
+    };
+
+    Some(Contact {
+        point: (overlap_min + overlap_max) * 0.5,
+        normal,
+        depth: overlap[axis],
+    })
+}
+
+// Separating axis test that also tracks the axis of least penetration
+fn obb_contact(a: &Obb, b: &Obb) -> Option<Contact> {
+    let offset = a.center - b.center;
+
+    let projected_radius = |obb: &Obb, axis: glam::Vec3| {
+        obb.axes[0].dot(axis).abs() * obb.half_extents.x
+            + obb.axes[1].dot(axis).abs() * obb.half_extents.y
+            + obb.axes[2].dot(axis).abs() * obb.half_extents.z
+    };
+
+    let mut best: Option<(f32, glam::Vec3)> = None;
+
+    let face_axes = a
+        .axes
+        .iter()
+        .chain(b.axes.iter())
+        .map(|axis| (*axis, false));
+    let edge_axes = a.axes.iter().flat_map(|a_axis| {
+        b.axes
+            .iter()
+            .map(move |b_axis| (a_axis.cross(*b_axis), true))
+    });
+
+    for (axis, is_edge) in face_axes.chain(edge_axes) {
+        let length = axis.length();
+
+        // Parallel edges don't give a usable axis
+        if length < CONTACT_AXIS_EPSILON {
+            continue;
+        }
+
+        let axis = axis / length;
+        let distance = offset.dot(axis);
+        let overlap = projected_radius(a, axis) + projected_radius(b, axis) - distance.abs();
+
+        if overlap < 0. {
+            return None;
+        }
+
+        // Favour face axes so resting boxes don't jitter between near equal edge axes
+        let biased = match is_edge {
+            true => overlap * EDGE_AXIS_BIAS,
+            false => overlap,
+        };
+
+        let is_best = match best {
+            Some((best_overlap, _)) => biased < best_overlap,
+            None => true,
+        };
+
+        if is_best {
+            let normal = match distance >= 0. {
+                true => axis,
+                false => -axis,
+            };
+
+            best = Some((biased, normal));
+        }
+    }
+
+    let (_, normal) = best?;
+    let depth = projected_radius(a, normal) + projected_radius(b, normal) - offset.dot(normal);
+
+    // Corners of either box poking into the other give the contact area. Crossed edges
+    // leave none inside, so fall back to the deepest corners of both boxes.
+    let tolerance = depth.max(0.) * 0.5 + CONTACT_TOLERANCE;
+    let inside = a
+        .vertices()
+        .into_iter()
+        .filter(|vertex| b.contains(*vertex, tolerance))
+        .chain(
+            b.vertices()
+                .into_iter()
+                .filter(|vertex| a.contains(*vertex, tolerance)),
+        )
+        .collect::<Vec<_>>();
+
+    let point = match inside.is_empty() {
+        false => inside.iter().sum::<glam::Vec3>() / inside.len() as f32,
+        true => (deepest_vertex(a, -normal) + deepest_vertex(b, normal)) * 0.5,
+    };
+
+    Some(Contact {
+        point,
+        normal,
+        depth: depth.max(0.),
+    })
+}
+
+#[inline]
+fn deepest_vertex(obb: &Obb, direction: glam::Vec3) -> glam::Vec3 {
+    obb.vertices()
+        .into_iter()
+        .max_by(|a, b| a.dot(direction).total_cmp(&b.dot(direction)))
+        .unwrap()
+}
+
+// Normal points from the box towards the sphere / capsule point
+fn aabb_round_contact(
+    aabb: &(Range, Range, Range),
+    center: glam::Vec3,
+    radius: f32,
+) -> Option<Contact> {
+    let closest = closest_point_on_aabb(aabb, center);
+    let offset = center - closest;
+    let distance_squared = offset.length_squared();
+
+    if distance_squared > radius * radius {
+        return None;
+    }
+
+    if distance_squared > f32::EPSILON {
+        let distance = distance_squared.sqrt();
+
+        return Some(Contact {
+            point: closest,
+            normal: offset / distance,
+            depth: radius - distance,
+        });
+    }
+
+    // Center is inside the box so push out through the nearest face
+    let faces = [
+        (center.x - aabb.0.min, -glam::Vec3::X),
+        (aabb.0.max - center.x, glam::Vec3::X),
+        (center.y - aabb.1.min, -glam::Vec3::Y),
+        (aabb.1.max - center.y, glam::Vec3::Y),
+        (center.z - aabb.2.min, -glam::Vec3::Z),
+        (aabb.2.max - center.z, glam::Vec3::Z),
+    ];
+
+    let (distance, normal) = faces
+        .into_iter()
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .unwrap();
+
+    Some(Contact {
+        point: center + normal * distance,
+        normal,
+        depth: radius + distance,
+    })
+}
+
+#[inline]
+fn obb_round_contact(obb: &Obb, center: glam::Vec3, radius: f32) -> Option<Contact> {
+    aabb_round_contact(&obb.local_aabb(), obb.to_local(center), radius).map(|contact| Contact {
+        point: obb.to_world(contact.point),
+        normal: obb.direction_to_world(contact.normal),
+        depth: contact.depth,
+    })
+}
+
+#[inline]
+fn obb_closest_segment_point(obb: &Obb, start: glam::Vec3, end: glam::Vec3) -> glam::Vec3 {
+    let local =
+        closest_segment_point_to_aabb(&obb.local_aabb(), obb.to_local(start), obb.to_local(end));
+
+    obb.to_world(local)
+}
+
+// Normal points from b towards a
+fn round_contact(a: glam::Vec3, a_radius: f32, b: glam::Vec3, b_radius: f32) -> Option<Contact> {
+    let offset = a - b;
+    let distance_squared = offset.length_squared();
+
+    if distance_squared > (a_radius + b_radius).powi(2) {
+        return None;
+    }
+
+    let distance = distance_squared.sqrt();
+    let normal = match distance > f32::EPSILON {
+        true => offset / distance,
+        false => glam::Vec3::Y,
+    };
+
+    Some(Contact {
+        point: b + normal * b_radius,
+        normal,
+        depth: a_radius + b_radius - distance,
+    })
+}
+
+//====================================================================
+
+// Narrows down the first time (0..1) within a window of travel that a moving box
+// collides, as the last free time and first blocked one. Samples no further apart
+// than the box is thick can't skip over anything.
+fn sample_sweep(
+    moving: &Aabb,
+    displacement: glam::Vec3,
+    (enter, exit): (f32, f32),
+    collides_at: impl Fn(f32) -> bool,
+) -> Option<(f32, f32)> {
+    let thickness = (moving.max - moving.min).min_element().max(f32::EPSILON);
+    let samples = (((exit - enter) * displacement.length() / thickness).ceil() as u32).max(1);
+    let spacing = (exit - enter) / samples as f32;
+
+    let mut blocked = (0..=samples)
+        .map(|sample| enter + sample as f32 * spacing)
+        .find(|time| *time > 0. && collides_at(*time))?;
+    let mut free = (blocked - spacing).max(0.);
+
+    for _ in 0..SWEEP_STEPS {
+        let mid = (free + blocked) * 0.5;
+
+        match collides_at(mid) {
+            true => blocked = mid,
+            false => free = mid,
+        }
+    }
+
+    Some((free, blocked))
+}
+
+// Time of impact (0..1) of a box moving by displacement into another box, along
+// with the normal of the face it hits. None if they don't meet or already overlap.
+fn swept_aabb(moving: &Aabb, displacement: glam::Vec3, target: &Aabb) -> Option<(f32, glam::Vec3)> {
+    let mut entry = glam::Vec3::splat(f32::NEG_INFINITY);
+    let mut exit = glam::Vec3::splat(f32::INFINITY);
+
+    for axis in 0..3 {
+        let (moving_min, moving_max) = (moving.min[axis], moving.max[axis]);
+        let (target_min, target_max) = (target.min[axis], target.max[axis]);
+        let distance = displacement[axis];
+
+        match distance {
+            distance if distance > 0. => {
+                entry[axis] = (target_min - moving_max) / distance;
+                exit[axis] = (target_max - moving_min) / distance;
+            }
+            distance if distance < 0. => {
+                entry[axis] = (target_max - moving_min) / distance;
+                exit[axis] = (target_min - moving_max) / distance;
+            }
+            _ => {
+                if moving_max < target_min || moving_min > target_max {
+                    return None;
+                }
+            }
+        }
+    }
+
+    let entry_time = entry.max_element();
+    let exit_time = exit.min_element();
+
+    if entry_time > exit_time || !(0. ..=1.).contains(&entry_time) {
+        return None;
+    }
+
+    let axis = match (entry.x >= entry.y && entry.x >= entry.z, entry.y >= entry.z) {
+        (true, _) => 0,
+        (false, true) => 1,
+        (false, false) => 2,
+    };
+
+    let mut normal = glam::Vec3::ZERO;
+    normal[axis] = -displacement[axis].signum();
+
+    Some((entry_time, normal))
+}
+
+//====================================================================
+
+fn ray_box(
+    min: glam::Vec3,
+    max: glam::Vec3,
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    max_distance: f32,
+) -> Option<(f32, glam::Vec3)> {
+    let mut entry = f32::NEG_INFINITY;
+    let mut exit = max_distance;
+    let mut normal = -direction;
+
+    for axis in 0..3 {
+        if direction[axis].abs() <= f32::EPSILON {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+
+        let near = (min[axis] - origin[axis]) / direction[axis];
+        let far = (max[axis] - origin[axis]) / direction[axis];
+        let (near, far) = (near.min(far), near.max(far));
+
+        if near > entry {
+            entry = near;
+            normal = glam::Vec3::ZERO;
+            normal[axis] = -direction[axis].signum();
+        }
+
+        exit = exit.min(far);
+    }
+
+    if entry > exit || exit < 0. {
+        return None;
+    }
+
+    match entry <= 0. {
+        true => Some((0., -direction)),
+        false => Some((entry, normal)),
+    }
+}
+
+fn ray_sphere(
+    center: glam::Vec3,
+    radius: f32,
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    max_distance: f32,
+) -> Option<(f32, glam::Vec3)> {
+    let offset = origin - center;
+    let c = offset.length_squared() - radius * radius;
+
+    if c <= 0. {
+        return Some((0., -direction));
+    }
+
+    let b = offset.dot(direction);
+    let discriminant = b * b - c;
+
+    if discriminant < 0. {
+        return None;
+    }
+
+    let distance = -b - discriminant.sqrt();
+
+    if !(0. ..=max_distance).contains(&distance) {
+        return None;
+    }
+
+    let normal = (origin + direction * distance - center) / radius;
+    Some((distance, normal))
+}
+
+fn ray_capsule(
+    start: glam::Vec3,
+    end: glam::Vec3,
+    radius: f32,
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    max_distance: f32,
+) -> Option<(f32, glam::Vec3)> {
+    if closest_point_on_segment(start, end, origin).distance_squared(origin) <= radius * radius {
+        return Some((0., -direction));
+    }
+
+    let caps = [start, end]
+        .into_iter()
+        .filter_map(|center| ray_sphere(center, radius, origin, direction, max_distance));
+
+    let segment = end - start;
+    let length = segment.length();
+
+    // Body of the capsule is an infinite cylinder clipped to the segment
+    let body = match length > f32::EPSILON {
+        true => {
+            let axis = segment / length;
+            let offset = origin - start;
+
+            let direction_perp = direction - axis * direction.dot(axis);
+            let offset_perp = offset - axis * offset.dot(axis);
+
+            let a = direction_perp.length_squared();
+            let b = offset_perp.dot(direction_perp);
+            let c = offset_perp.length_squared() - radius * radius;
+            let discriminant = b * b - a * c;
+
+            match a > f32::EPSILON && discriminant >= 0. {
+                true => {
+                    let distance = (-b - discriminant.sqrt()) / a;
+                    let along = (offset + direction * distance).dot(axis);
+
+                    match (0. ..=max_distance).contains(&distance)
+                        && (0. ..=length).contains(&along)
+                    {
+                        true => {
+                            Some((distance, (offset_perp + direction_perp * distance) / radius))
+                        }
+                        false => None,
+                    }
+                }
+                false => None,
+            }
+        }
+        false => None,
+    };
+
+    caps.chain(body).min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+//====================================================================
+
+fn circle_edges(
+    center: glam::Vec3,
+    normal: glam::Vec3,
+    radius: f32,
+) -> impl Iterator<Item = (glam::Vec3, glam::Vec3)> {
+    let (a, b) = normal.any_orthonormal_pair();
+    let point = move |segment: u32| {
+        let angle = segment as f32 / WIREFRAME_CIRCLE_SEGMENTS as f32 * std::f32::consts::TAU;
+        center + (a * angle.cos() + b * angle.sin()) * radius
+    };
+
+    (0..WIREFRAME_CIRCLE_SEGMENTS).map(move |segment| (point(segment), point(segment + 1)))
+}
+
+#[inline]
+fn aabb_from_center(center: glam::Vec3, half_extents: glam::Vec3) -> (Range, Range, Range) {
+    (
+        Range {
+            min: center.x - half_extents.x,
+            max: center.x + half_extents.x,
+        },
+        Range {
+            min: center.y - half_extents.y,
+            max: center.y + half_extents.y,
+        },
+        Range {
+            min: center.z - half_extents.z,
+            max: center.z + half_extents.z,
+        },
+    )
+}
+
+#[inline]
+fn is_axis_aligned(rotation: &glam::Mat3) -> bool {
+    [rotation.x_axis, rotation.y_axis, rotation.z_axis]
+        .iter()
+        .all(|axis| axis.abs().max_element() >= 1. - AXIS_ALIGNED_EPSILON)
+}
+
+#[inline]
+fn closest_point_on_aabb(aabb: &(Range, Range, Range), point: glam::Vec3) -> glam::Vec3 {
+    glam::vec3(
+        point.x.clamp(aabb.0.min, aabb.0.max),
+        point.y.clamp(aabb.1.min, aabb.1.max),
+        point.z.clamp(aabb.2.min, aabb.2.max),
+    )
+}
+
+fn closest_segment_point_to_aabb(
+    aabb: &(Range, Range, Range),
+    start: glam::Vec3,
+    end: glam::Vec3,
+) -> glam::Vec3 {
+    // Distance from a point to a box is convex, so it is also convex along the
+    // segment and a ternary search will find the closest point.
+    let distance_at = |t: f32| {
+        let point = start.lerp(end, t);
+        closest_point_on_aabb(aabb, point).distance_squared(point)
+    };
+
+    let mut low = 0.;
+    let mut high = 1.;
+
+    for _ in 0..SEGMENT_SEARCH_STEPS {
+        let a = low + (high - low) / 3.;
+        let b = high - (high - low) / 3.;
+
+        match distance_at(a) <= distance_at(b) {
+            true => high = b,
+            false => low = a,
+        }
+    }
+
+    start.lerp(end, (low + high) * 0.5)
+}
+
+#[inline]
+fn closest_point_on_segment(start: glam::Vec3, end: glam::Vec3, point: glam::Vec3) -> glam::Vec3 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+
+    if length_squared <= f32::EPSILON {
+        return start;
+    }
+
+    let t = ((point - start).dot(segment) / length_squared).clamp(0., 1.);
+    start + segment * t
+}
+
+fn closest_points_between_segments(
+    a_start: glam::Vec3,
+    a_end: glam::Vec3,
+    b_start: glam::Vec3,
+    b_end: glam::Vec3,
+) -> (glam::Vec3, glam::Vec3) {
+    let a_dir = a_end - a_start;
+    let b_dir = b_end - b_start;
+    let offset = a_start - b_start;
+
+    let a_length = a_dir.length_squared();
+    let b_length = b_dir.length_squared();
+    let f = b_dir.dot(offset);
+
+    if a_length <= f32::EPSILON && b_length <= f32::EPSILON {
+        return (a_start, b_start);
+    }
+
+    let (s, t) = match (a_length <= f32::EPSILON, b_length <= f32::EPSILON) {
+        (true, _) => (0., (f / b_length).clamp(0., 1.)),
+        (false, true) => ((-a_dir.dot(offset) / a_length).clamp(0., 1.), 0.),
+        (false, false) => {
+            let c = a_dir.dot(offset);
+            let b = a_dir.dot(b_dir);
+            let denom = a_length * b_length - b * b;
+
+            let s = match denom != 0. {
+                true => ((b * f - c * b_length) / denom).clamp(0., 1.),
+                false => 0.,
+            };
+
+            let t = (b * s + f) / b_length;
+
+            match t {
+                t if t < 0. => ((-c / a_length).clamp(0., 1.), 0.),
+                t if t > 1. => (((b - c) / a_length).clamp(0., 1.), 1.),
+                t => (s, t),
+            }
+        }
+    };
+
+    (a_start + a_dir * s, b_start + b_dir * t)
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sweep_misses_empty_corners() {
+        let moving = Aabb::new(glam::vec3(12., 28., -2.), glam::vec3(16., 32., 2.));
+
+        // Square turned onto its corner, whose bounds reach up to 14.1 above x = 14
+        let diamond = PreppedCollisionShape::from_collision_shape(
+            &CollisionShape::Box {
+                half_width: 10.,
+                half_height: 10.,
+                half_depth: 10.,
+            },
+            glam::Vec3::ZERO,
+            glam::Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+            glam::Vec3::ONE,
+        );
+
+        let (time, normal) = diamond.sweep(&moving, glam::vec3(0., -40., 0.)).unwrap();
+        let expected = (28. - (200_f32.sqrt() - 12.)) / 40.;
+
+        assert!((time - expected).abs() < 0.01, "{time} != {expected}");
+        assert!(normal.x > 0. && normal.y > 0., "{normal}");
+
+        // Passes by the sphere's bounds corner without touching it
+        let sphere = PreppedCollisionShape::from_collision_shape(
+            &CollisionShape::Sphere { radius: 10. },
+            glam::Vec3::ZERO,
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        );
+        let moving = Aabb::new(glam::vec3(-32., 9., 9.), glam::vec3(-28., 13., 13.));
+
+        assert!(sphere.sweep(&moving, glam::vec3(60., 0., 0.)).is_none());
+    }
+}
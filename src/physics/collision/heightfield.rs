@@ -0,0 +1,252 @@
+//====================================================================
+
+use std::{fmt, sync::Arc};
+
+use super::TriMesh;
+
+//====================================================================
+
+// Terrain made from a grid of heights, stored a row at a time along x with rows
+// going along z. Points are cell_size apart and the grid is centred on its entity.
+// Collision goes through the same triangles mesh_buffers hands out for rendering.
+pub struct Heightfield {
+    columns: usize,
+    rows: usize,
+    cell_size: f32,
+    heights: Vec<f32>,
+    mesh: Arc<TriMesh>,
+}
+
+impl fmt::Debug for Heightfield {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Heightfield")
+            .field("columns", &self.columns)
+            .field("rows", &self.rows)
+            .field("cell_size", &self.cell_size)
+            .finish()
+    }
+}
+
+// Same as TriMesh, only counts as changed when swapped for another
+impl PartialEq for Heightfield {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl Heightfield {
+    // Missing heights are treated as 0 and extra ones are ignored
+    pub fn new(columns: usize, rows: usize, cell_size: f32, mut heights: Vec<f32>) -> Self {
+        let columns = columns.max(2);
+        let rows = rows.max(2);
+
+        heights.resize(columns * rows, 0.);
+
+        let mut heightfield = Self {
+            columns,
+            rows,
+            cell_size,
+            heights,
+            mesh: Arc::new(TriMesh::new(Vec::new(), &[])),
+        };
+
+        heightfield.mesh = Arc::new(TriMesh::new(
+            heightfield.positions().collect(),
+            &heightfield.indices(),
+        ));
+
+        heightfield
+    }
+
+    pub fn from_fn(
+        columns: usize,
+        rows: usize,
+        cell_size: f32,
+        height: impl Fn(usize, usize) -> f32,
+    ) -> Self {
+        let heights = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| height(column, row))
+            .collect();
+
+        Self::new(columns, rows, cell_size, heights)
+    }
+
+    #[inline]
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    #[inline]
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    #[allow(dead_code)]
+    #[inline]
+    pub fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    #[inline]
+    pub fn height(&self, column: usize, row: usize) -> f32 {
+        self.heights[row * self.columns + column]
+    }
+
+    // Vertex and index buffers for rendering the terrain. Vertex builds whatever vertex
+    // type the renderer wants from a position, normal and uv. Uvs cover the whole grid once.
+    pub fn mesh_buffers<V>(
+        &self,
+        vertex: impl Fn(glam::Vec3, glam::Vec3, glam::Vec2) -> V,
+    ) -> (Vec<V>, Vec<u32>) {
+        let vertices = self
+            .positions()
+            .enumerate()
+            .map(|(index, position)| {
+                let (column, row) = (index % self.columns, index / self.columns);
+                let uv = glam::vec2(
+                    column as f32 / (self.columns - 1) as f32,
+                    row as f32 / (self.rows - 1) as f32,
+                );
+
+                vertex(position, self.vertex_normal(column, row), uv)
+            })
+            .collect();
+
+        (vertices, self.indices())
+    }
+
+    #[inline]
+    pub(super) fn mesh(&self) -> &Arc<TriMesh> {
+        &self.mesh
+    }
+
+    #[inline]
+    fn corner(&self) -> glam::Vec2 {
+        glam::vec2(
+            (self.columns - 1) as f32 * self.cell_size,
+            (self.rows - 1) as f32 * self.cell_size,
+        ) * -0.5
+    }
+
+    #[inline]
+    fn position(&self, column: usize, row: usize) -> glam::Vec3 {
+        let corner = self.corner();
+
+        glam::vec3(
+            corner.x + column as f32 * self.cell_size,
+            self.height(column, row),
+            corner.y + row as f32 * self.cell_size,
+        )
+    }
+
+    fn positions(&self) -> impl Iterator<Item = glam::Vec3> + '_ {
+        (0..self.rows)
+            .flat_map(move |row| (0..self.columns).map(move |column| self.position(column, row)))
+    }
+
+    // Two triangles per cell, wound so their normals face up
+    fn indices(&self) -> Vec<u32> {
+        let columns = self.columns as u32;
+
+        (0..self.rows as u32 - 1)
+            .flat_map(|row| (0..columns - 1).map(move |column| row * columns + column))
+            .flat_map(|corner| {
+                let (a, b, c, d) = (corner, corner + 1, corner + columns, corner + columns + 1);
+                [a, d, b, a, c, d]
+            })
+            .collect()
+    }
+
+    // Smoothed from the heights either side for rendering
+    fn vertex_normal(&self, column: usize, row: usize) -> glam::Vec3 {
+        let left = self.height(column.saturating_sub(1), row);
+        let right = self.height((column + 1).min(self.columns - 1), row);
+        let back = self.height(column, row.saturating_sub(1));
+        let front = self.height(column, (row + 1).min(self.rows - 1));
+
+        glam::vec3(left - right, 2. * self.cell_size, back - front).normalize_or_zero()
+    }
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use hecs::{Entity, World};
+    use hecs_engine::common::Transform;
+
+    use super::*;
+    use crate::physics::{
+        tests::{push_action, run, spawn_character, spawn_mover, translation, TICK},
+        CollisionShape, Gravity, Grounded, MaxSlopeAngle, MaxStepHeight, MovementAction,
+        PhysicsHandler, StaticCollisionType,
+    };
+
+    // 800 across, centred on the origin. Height goes up along x by slope per unit.
+    fn spawn_terrain(world: &mut World, slope: f32) -> Entity {
+        let heightfield =
+            Heightfield::from_fn(41, 41, 20., |column, _| column as f32 * 20. * slope);
+
+        world.spawn((
+            Transform::default(),
+            StaticCollisionType,
+            CollisionShape::Heightfield(Arc::new(heightfield)),
+        ))
+    }
+
+    #[test]
+    fn lands_on_cell_edges_and_corners() {
+        // Corner shared by four cells, along an edge in each direction and mid cell
+        [(0., 0.), (10., 0.), (0., 10.), (10., 10.), (15., 5.)]
+            .into_iter()
+            .for_each(|(x, z)| {
+                let mut world = World::new();
+                spawn_terrain(&mut world, 0.);
+
+                let mover = spawn_mover(&mut world, glam::vec3(x, 30., z), glam::Vec3::ZERO);
+                world
+                    .insert_one(mover, Gravity(glam::vec3(0., -400., 0.)))
+                    .unwrap();
+
+                let mut physics = PhysicsHandler::default();
+                run(&mut physics, &mut world, 60);
+
+                let landed = translation(&world, mover);
+                assert!((landed.y - 5.).abs() < 0.5, "{landed}");
+            });
+    }
+
+    #[test]
+    fn walks_across_cells_without_snagging() {
+        let mut world = World::new();
+        spawn_terrain(&mut world, 0.1);
+
+        let character = spawn_character(&mut world, glam::vec3(-300., 31., 0.));
+        world
+            .insert(
+                character,
+                (MaxStepHeight(8.), MaxSlopeAngle(45_f32.to_radians())),
+            )
+            .unwrap();
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 30);
+        let start = translation(&world, character);
+
+        (0..90).for_each(|_| {
+            push_action(&world, character, MovementAction::Move((1., 0.)));
+            physics.tick(&mut world, TICK);
+        });
+
+        // Several cells further along and up the same slope
+        let end = translation(&world, character);
+        assert!(end.x - start.x > 100., "{start} {end}");
+        assert!(
+            ((end.y - start.y) - (end.x - start.x) * 0.1).abs() < 2.,
+            "{start} {end}"
+        );
+        assert!(world.get::<&Grounded>(character).is_ok());
+    }
+}
@@ -0,0 +1,815 @@
+//====================================================================
+
+use std::{collections::HashSet, fmt, sync::Arc};
+
+use super::{
+    closest_point_on_segment, closest_points_between_segments, ray_box, sample_sweep, Aabb,
+    Contact, Obb, PreppedCollisionShape,
+};
+
+//====================================================================
+
+const BVH_LEAF_SIZE: usize = 4;
+
+const TRIANGLE_AXIS_EPSILON: f32 = 0.0001;
+const TRIANGLE_CONTACT_TOLERANCE: f32 = 0.05;
+const TRIANGLE_EDGE_AXIS_BIAS: f32 = 1.05;
+
+//====================================================================
+
+// Collider for level geometry, built from the same vertex and index buffers as a
+// Mesh. Triangles are two sided so winding order doesn't matter. Static only.
+pub struct TriMesh {
+    vertices: Vec<glam::Vec3>,
+    triangles: Vec<[u32; 3]>,
+    nodes: Vec<BvhNode>,
+}
+
+// Bounding volume hierarchy over the triangles, built once in local space. Only the
+// layout is kept since bounds are refitted when prepped. Branches have a count of 0
+// with their left child right after them and their right child at first. Leaves
+// cover count triangles starting at first.
+struct BvhNode {
+    first: u32,
+    count: u32,
+}
+
+impl fmt::Debug for TriMesh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TriMesh")
+            .field("vertices", &self.vertices.len())
+            .field("triangles", &self.triangles.len())
+            .finish()
+    }
+}
+
+// Meshes are shared between colliders so only count as changed when swapped for another
+impl PartialEq for TriMesh {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self, other)
+    }
+}
+
+impl TriMesh {
+    // Triangles with out of range indices or no area are left out
+    pub fn new(vertices: Vec<glam::Vec3>, indices: &[u32]) -> Self {
+        let mut triangles = indices
+            .chunks_exact(3)
+            .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+            .filter(|triangle| {
+                match triangle
+                    .iter()
+                    .all(|index| (*index as usize) < vertices.len())
+                {
+                    true => {
+                        let [a, b, c] = triangle.map(|index| vertices[index as usize]);
+                        (b - a).cross(c - a).length_squared() > f32::EPSILON
+                    }
+                    false => false,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mut nodes = Vec::new();
+
+        if !triangles.is_empty() {
+            build_node(&mut nodes, &vertices, &mut triangles, 0);
+        }
+
+        Self {
+            vertices,
+            triangles,
+            nodes,
+        }
+    }
+
+    // Pulls positions out of any vertex type, eg. the buffers passed to Mesh::load_mesh
+    #[allow(dead_code)]
+    pub fn from_buffers<V, I>(
+        vertices: &[V],
+        indices: &[I],
+        position: impl Fn(&V) -> glam::Vec3,
+    ) -> Self
+    where
+        I: Copy + Into<u32>,
+    {
+        let indices = indices
+            .iter()
+            .map(|index| (*index).into())
+            .collect::<Vec<_>>();
+
+        Self::new(vertices.iter().map(position).collect(), &indices)
+    }
+
+    #[allow(dead_code)]
+    #[inline]
+    pub fn triangle_count(&self) -> usize {
+        self.triangles.len()
+    }
+}
+
+fn build_node(
+    nodes: &mut Vec<BvhNode>,
+    vertices: &[glam::Vec3],
+    triangles: &mut [[u32; 3]],
+    first: usize,
+) {
+    let corners = |triangle: &[u32; 3]| triangle.map(|index| vertices[index as usize]);
+    let centroid = |triangle: &[u32; 3]| corners(triangle).into_iter().sum::<glam::Vec3>() / 3.;
+
+    let index = nodes.len();
+    nodes.push(BvhNode {
+        first: first as u32,
+        count: triangles.len() as u32,
+    });
+
+    if triangles.len() <= BVH_LEAF_SIZE {
+        return;
+    }
+
+    // Split down the middle of the longest axis the triangle centres are spread over
+    let spread = points_bounds(triangles.iter().map(centroid));
+    let extent = spread.max - spread.min;
+
+    let axis = match (
+        extent.x >= extent.y && extent.x >= extent.z,
+        extent.y >= extent.z,
+    ) {
+        (true, _) => 0,
+        (false, true) => 1,
+        (false, false) => 2,
+    };
+
+    let middle = triangles.len() / 2;
+    triangles.select_nth_unstable_by(middle, |a, b| {
+        centroid(a)[axis].total_cmp(&centroid(b)[axis])
+    });
+
+    let (left, right) = triangles.split_at_mut(middle);
+
+    build_node(nodes, vertices, left, first);
+    let right_index = nodes.len();
+    build_node(nodes, vertices, right, first + middle);
+
+    nodes[index].first = right_index as u32;
+    nodes[index].count = 0;
+}
+
+//====================================================================
+
+// Mesh moved into world space. The hierarchy keeps its shape and just has its
+// bounds refitted around the moved triangles.
+pub(in crate::physics) struct PreppedTriMesh {
+    mesh: Arc<TriMesh>,
+    vertices: Vec<glam::Vec3>,
+    bounds: Vec<Aabb>,
+    origin: glam::Vec3,
+}
+
+impl PreppedTriMesh {
+    pub fn new(
+        mesh: &Arc<TriMesh>,
+        translation: glam::Vec3,
+        rotation: glam::Quat,
+        scale: glam::Vec3,
+    ) -> Self {
+        let vertices = mesh
+            .vertices
+            .iter()
+            .map(|vertex| translation + rotation * (*vertex * scale))
+            .collect::<Vec<_>>();
+
+        let mut bounds = vec![Aabb::new(translation, translation); mesh.nodes.len()];
+
+        // Children always come after their parent so refit from the back
+        (0..mesh.nodes.len()).rev().for_each(|index| {
+            let node = &mesh.nodes[index];
+
+            bounds[index] = match node.count {
+                0 => bounds[index + 1].union(&bounds[node.first as usize]),
+                count => points_bounds(
+                    mesh.triangles[node.first as usize..(node.first + count) as usize]
+                        .iter()
+                        .flat_map(|triangle| triangle.map(|index| vertices[index as usize])),
+                ),
+            };
+        });
+
+        Self {
+            mesh: mesh.clone(),
+            vertices,
+            bounds,
+            origin: translation,
+        }
+    }
+
+    #[inline]
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+            .first()
+            .copied()
+            .unwrap_or(Aabb::new(self.origin, self.origin))
+    }
+
+    pub fn check_collision(&self, other: &PreppedCollisionShape) -> bool {
+        self.triangles_near(&other.bounds())
+            .iter()
+            .any(|triangle| triangle_contact(triangle, other).is_some())
+    }
+
+    // Normal points out of the mesh towards the other shape. Contacts pushing out through
+    // a triangle's face are preferred over ones pushing out past the edges between them.
+    pub fn contact(&self, other: &PreppedCollisionShape) -> Option<Contact> {
+        self.triangles_near(&other.bounds())
+            .iter()
+            .filter_map(|triangle| {
+                let contact = triangle_contact(triangle, other)?;
+                let through_face = contact.normal.dot(triangle_normal(triangle)).abs()
+                    >= 1. - TRIANGLE_AXIS_EPSILON;
+
+                Some((through_face, contact))
+            })
+            .max_by(|(a_face, a), (b_face, b)| a_face.cmp(b_face).then(a.depth.total_cmp(&b.depth)))
+            .map(|(_, contact)| contact)
+    }
+
+    // Same as swept_aabb, but against the triangles rather than the mesh bounds
+    pub fn sweep(&self, moving: &Aabb, displacement: glam::Vec3) -> Option<(f32, glam::Vec3)> {
+        let swept = moving.union(&Aabb::new(
+            moving.min + displacement,
+            moving.max + displacement,
+        ));
+        let triangles = self.triangles_near(&swept);
+
+        if triangles.is_empty() {
+            return None;
+        }
+
+        let center = (moving.min + moving.max) * 0.5;
+        let half_extents = (moving.max - moving.min) * 0.5;
+
+        let box_at = |time: f32| Obb {
+            center: center + displacement * time,
+            half_extents,
+            axes: [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z],
+        };
+        let contact_at = |time: f32| {
+            let moved = box_at(time);
+
+            triangles
+                .iter()
+                .filter_map(|triangle| box_triangle_contact(&moved, triangle))
+                .max_by(|a, b| a.depth.total_cmp(&b.depth))
+        };
+
+        if contact_at(0.).is_some() {
+            return None;
+        }
+
+        // Nothing to hit outside of the mesh bounds, so only sample while inside them
+        let window = moving.overlap_window(displacement, &self.bounds())?;
+        let (free, blocked) = sample_sweep(moving, displacement, window, |time| {
+            contact_at(time).is_some()
+        })?;
+
+        contact_at(blocked).map(|contact| (free, contact.normal))
+    }
+
+    pub fn raycast(
+        &self,
+        origin: glam::Vec3,
+        direction: glam::Vec3,
+        max_distance: f32,
+    ) -> Option<(f32, glam::Vec3)> {
+        let mut closest: Option<(f32, glam::Vec3)> = None;
+        let mut stack = self.root();
+
+        while let Some(index) = stack.pop() {
+            let limit = closest
+                .map(|(distance, _)| distance)
+                .unwrap_or(max_distance);
+            let bounds = &self.bounds[index];
+
+            if ray_box(bounds.min, bounds.max, origin, direction, limit).is_none() {
+                continue;
+            }
+
+            let node = &self.mesh.nodes[index];
+
+            if node.count == 0 {
+                stack.extend([index + 1, node.first as usize]);
+                continue;
+            }
+
+            for triangle in self.leaf_triangles(node) {
+                let limit = closest
+                    .map(|(distance, _)| distance)
+                    .unwrap_or(max_distance);
+
+                if let Some(distance) = ray_triangle(origin, direction, &triangle, limit) {
+                    let normal = triangle_normal(&triangle);
+
+                    // Two sided, so face whichever way the ray came from
+                    let normal = match normal.dot(direction) > 0. {
+                        true => -normal,
+                        false => normal,
+                    };
+
+                    closest = Some((distance, normal));
+                }
+            }
+        }
+
+        closest
+    }
+
+    // Shared edges are only drawn once
+    pub fn wireframe(&self) -> Vec<(glam::Vec3, glam::Vec3)> {
+        let mut drawn = HashSet::new();
+
+        self.mesh
+            .triangles
+            .iter()
+            .flat_map(|[a, b, c]| [(*a, *b), (*b, *c), (*c, *a)])
+            .filter(|(a, b)| drawn.insert((*a.min(b), *a.max(b))))
+            .map(|(a, b)| (self.vertices[a as usize], self.vertices[b as usize]))
+            .collect()
+    }
+
+    #[inline]
+    fn root(&self) -> Vec<usize> {
+        match self.bounds.is_empty() {
+            true => Vec::new(),
+            false => vec![0],
+        }
+    }
+
+    #[inline]
+    fn leaf_triangles<'a>(&'a self, node: &BvhNode) -> impl Iterator<Item = [glam::Vec3; 3]> + 'a {
+        self.mesh.triangles[node.first as usize..(node.first + node.count) as usize]
+            .iter()
+            .map(|triangle| triangle.map(|index| self.vertices[index as usize]))
+    }
+
+    fn triangles_near(&self, bounds: &Aabb) -> Vec<[glam::Vec3; 3]> {
+        let mut triangles = Vec::new();
+        let mut stack = self.root();
+
+        while let Some(index) = stack.pop() {
+            if !self.bounds[index].intersects(bounds) {
+                continue;
+            }
+
+            let node = &self.mesh.nodes[index];
+
+            match node.count {
+                0 => stack.extend([index + 1, node.first as usize]),
+                _ => triangles.extend(self.leaf_triangles(node)),
+            }
+        }
+
+        triangles
+    }
+}
+
+//====================================================================
+
+// Normal points out of the triangle towards the shape
+fn triangle_contact(triangle: &[glam::Vec3; 3], shape: &PreppedCollisionShape) -> Option<Contact> {
+    match shape {
+        PreppedCollisionShape::Box(aabb) => box_triangle_contact(&Obb::from_aabb(aabb), triangle),
+        PreppedCollisionShape::Obb(obb) => box_triangle_contact(obb, triangle),
+
+        PreppedCollisionShape::Sphere { center, radius } => {
+            round_triangle_contact(*center, *center, *radius, triangle)
+        }
+
+        PreppedCollisionShape::Capsule { start, end, radius } => {
+            round_triangle_contact(*start, *end, *radius, triangle)
+        }
+
+        // Meshes are static only so never need testing against each other, and
+        // compounds are split into their parts before getting here
+        PreppedCollisionShape::TriMesh(_) | PreppedCollisionShape::Compound(_) => None,
+    }
+}
+
+// Separating axis test in the box's local space, tracking the axis of least penetration
+fn box_triangle_contact(obb: &Obb, triangle: &[glam::Vec3; 3]) -> Option<Contact> {
+    let local = triangle.map(|vertex| obb.to_local(vertex));
+    let edges = [
+        local[1] - local[0],
+        local[2] - local[1],
+        local[0] - local[2],
+    ];
+
+    let box_axes = [glam::Vec3::X, glam::Vec3::Y, glam::Vec3::Z];
+
+    let face_axes = box_axes
+        .into_iter()
+        .chain([edges[0].cross(edges[1])])
+        .map(|axis| (axis, false));
+    let edge_axes = box_axes
+        .into_iter()
+        .flat_map(|box_axis| edges.map(|edge| (box_axis.cross(edge), true)));
+
+    let mut best: Option<(f32, glam::Vec3, f32)> = None;
+
+    for (axis, is_edge) in face_axes.chain(edge_axes) {
+        let length = axis.length();
+
+        if length < TRIANGLE_AXIS_EPSILON {
+            continue;
+        }
+
+        let axis = axis / length;
+        let radius = obb.half_extents.dot(axis.abs());
+
+        let projected = local.map(|vertex| vertex.dot(axis));
+        let min = projected[0].min(projected[1]).min(projected[2]);
+        let max = projected[0].max(projected[1]).max(projected[2]);
+
+        // Box centre projects to 0, so push it out whichever way is shorter
+        let (depth, normal) = match max + radius <= radius - min {
+            true => (max + radius, axis),
+            false => (radius - min, -axis),
+        };
+
+        if depth < 0. {
+            return None;
+        }
+
+        let biased = match is_edge {
+            true => depth * TRIANGLE_EDGE_AXIS_BIAS,
+            false => depth,
+        };
+
+        let is_best = match best {
+            Some((best_depth, _, _)) => biased < best_depth,
+            None => true,
+        };
+
+        if is_best {
+            best = Some((biased, normal, depth));
+        }
+    }
+
+    let (_, normal, depth) = best?;
+
+    // Boxes over a triangle are pushed out through its face. Otherwise a box sunk into
+    // a flat mesh can be pushed sideways out through the edges between triangles.
+    let face = triangle_normal(&local);
+    let offset = face.dot(local[0]);
+    let over_face = closest_point_on_triangle(face * offset, &local).distance(face * offset)
+        <= TRIANGLE_CONTACT_TOLERANCE;
+
+    let (normal, depth) = match (over_face, offset <= 0.) {
+        (true, true) => (face, offset + obb.half_extents.dot(face.abs())),
+        (true, false) => (-face, obb.half_extents.dot(face.abs()) - offset),
+        (false, _) => (normal, depth),
+    };
+
+    // Corners of the triangle inside the box give the contact area, otherwise use
+    // the box corner furthest into the triangle
+    let tolerance = glam::Vec3::splat(depth * 0.5 + TRIANGLE_CONTACT_TOLERANCE);
+    let inside = local
+        .into_iter()
+        .filter(|vertex| vertex.abs().cmple(obb.half_extents + tolerance).all())
+        .collect::<Vec<_>>();
+
+    let point = match inside.is_empty() {
+        false => inside.iter().sum::<glam::Vec3>() / inside.len() as f32,
+        true => -normal.signum() * obb.half_extents + normal * depth * 0.5,
+    };
+
+    Some(Contact {
+        point: obb.to_world(point),
+        normal: obb.direction_to_world(normal),
+        depth,
+    })
+}
+
+// Spheres are capsules with no length
+fn round_triangle_contact(
+    start: glam::Vec3,
+    end: glam::Vec3,
+    radius: f32,
+    triangle: &[glam::Vec3; 3],
+) -> Option<Contact> {
+    let (segment_point, triangle_point) = closest_segment_triangle_points(start, end, triangle);
+
+    let offset = segment_point - triangle_point;
+    let distance_squared = offset.length_squared();
+
+    if distance_squared > radius * radius {
+        return None;
+    }
+
+    let distance = distance_squared.sqrt();
+
+    if distance > f32::EPSILON {
+        return Some(Contact {
+            point: triangle_point,
+            normal: offset / distance,
+            depth: radius - distance,
+        });
+    }
+
+    // Touching or passing through the triangle, so push out on the side most of it is on
+    let face = triangle_normal(triangle);
+    let sides = [start, end].map(|point| (point - triangle[0]).dot(face));
+
+    let (normal, sides) = match sides[0] + sides[1] >= 0. {
+        true => (face, sides),
+        false => (-face, sides.map(|side| -side)),
+    };
+
+    Some(Contact {
+        point: triangle_point,
+        normal,
+        depth: radius - sides[0].min(sides[1]),
+    })
+}
+
+fn closest_segment_triangle_points(
+    start: glam::Vec3,
+    end: glam::Vec3,
+    triangle: &[glam::Vec3; 3],
+) -> (glam::Vec3, glam::Vec3) {
+    if let Some(time) = ray_triangle(start, end - start, triangle, 1.) {
+        let point = start + (end - start) * time;
+        return (point, point);
+    }
+
+    // Otherwise the closest points are on an end of the segment or an edge of the triangle
+    let [a, b, c] = *triangle;
+
+    let ends = [start, end]
+        .into_iter()
+        .map(|point| (point, closest_point_on_triangle(point, triangle)));
+    let edges = [(a, b), (b, c), (c, a)]
+        .into_iter()
+        .map(|(edge_start, edge_end)| {
+            closest_points_between_segments(start, end, edge_start, edge_end)
+        });
+
+    ends.chain(edges)
+        .min_by(|(a, a_other), (b, b_other)| {
+            a.distance_squared(*a_other)
+                .total_cmp(&b.distance_squared(*b_other))
+        })
+        .unwrap()
+}
+
+fn closest_point_on_triangle(point: glam::Vec3, triangle: &[glam::Vec3; 3]) -> glam::Vec3 {
+    let [a, b, c] = *triangle;
+
+    let ab = b - a;
+    let ac = c - a;
+
+    let ap = point - a;
+    let d1 = ab.dot(ap);
+    let d2 = ac.dot(ap);
+
+    if d1 <= 0. && d2 <= 0. {
+        return a;
+    }
+
+    let bp = point - b;
+    let d3 = ab.dot(bp);
+    let d4 = ac.dot(bp);
+
+    if d3 >= 0. && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+
+    if vc <= 0. && d1 >= 0. && d3 <= 0. {
+        return a + ab * (d1 / (d1 - d3));
+    }
+
+    let cp = point - c;
+    let d5 = ab.dot(cp);
+    let d6 = ac.dot(cp);
+
+    if d6 >= 0. && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+
+    if vb <= 0. && d2 >= 0. && d6 <= 0. {
+        return a + ac * (d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+
+    if va <= 0. && d4 - d3 >= 0. && d5 - d6 >= 0. {
+        return closest_point_on_segment(b, c, point);
+    }
+
+    let denom = 1. / (va + vb + vc);
+    a + ab * (vb * denom) + ac * (vc * denom)
+}
+
+// Distance along direction to where a ray crosses a triangle, from either side
+fn ray_triangle(
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    triangle: &[glam::Vec3; 3],
+    max_distance: f32,
+) -> Option<f32> {
+    let [a, b, c] = *triangle;
+
+    let ab = b - a;
+    let ac = c - a;
+
+    let p = direction.cross(ac);
+    let determinant = ab.dot(p);
+
+    if determinant.abs() <= f32::EPSILON {
+        return None;
+    }
+
+    let inverse = 1. / determinant;
+    let offset = origin - a;
+
+    let u = offset.dot(p) * inverse;
+
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+
+    let q = offset.cross(ab);
+    let v = direction.dot(q) * inverse;
+
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    let distance = ac.dot(q) * inverse;
+
+    match (0. ..=max_distance).contains(&distance) {
+        true => Some(distance),
+        false => None,
+    }
+}
+
+#[inline]
+fn triangle_normal(triangle: &[glam::Vec3; 3]) -> glam::Vec3 {
+    (triangle[1] - triangle[0])
+        .cross(triangle[2] - triangle[0])
+        .normalize_or_zero()
+}
+
+#[inline]
+fn points_bounds(points: impl Iterator<Item = glam::Vec3>) -> Aabb {
+    let (min, max) = points.fold(
+        (glam::Vec3::INFINITY, glam::Vec3::NEG_INFINITY),
+        |(min, max), point| (min.min(point), max.max(point)),
+    );
+
+    Aabb::new(min, max)
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::CollisionShape;
+
+    // Square grid of cells with two triangles each, offset in height by bumps
+    fn grid_mesh(cells: u32, cell_size: f32, bumps: impl Fn(f32, f32) -> f32) -> Arc<TriMesh> {
+        let width = cells + 1;
+
+        let vertices = (0..width * width)
+            .map(|index| {
+                let x = (index % width) as f32 * cell_size;
+                let z = (index / width) as f32 * cell_size;
+                glam::vec3(x, bumps(x, z), z)
+            })
+            .collect::<Vec<_>>();
+
+        let indices = (0..cells * cells)
+            .flat_map(|cell| {
+                let a = cell / cells * width + cell % cells;
+                [a, a + 1, a + width + 1, a, a + width + 1, a + width]
+            })
+            .collect::<Vec<_>>();
+
+        Arc::new(TriMesh::new(vertices, &indices))
+    }
+
+    #[test]
+    fn raycast_matches_brute_force() {
+        let mesh = grid_mesh(24, 10., |x, z| {
+            (x * 0.13).sin() * 8. + (z * 0.07).cos() * 5.
+        });
+        let prepped = PreppedTriMesh::new(
+            &mesh,
+            glam::vec3(-50., 3., 20.),
+            glam::Quat::from_rotation_y(0.4),
+            glam::Vec3::ONE,
+        );
+
+        let triangles = mesh
+            .triangles
+            .iter()
+            .map(|triangle| triangle.map(|index| prepped.vertices[index as usize]))
+            .collect::<Vec<_>>();
+
+        (0..300).for_each(|index| {
+            let index = index as f32;
+            let origin = glam::vec3(
+                (index * 7.31) % 300. - 100.,
+                60.,
+                (index * 3.77) % 300. - 50.,
+            );
+            let direction =
+                glam::vec3((index * 0.7).sin() * 0.5, -1., (index * 1.3).cos() * 0.5).normalize();
+
+            let expected = triangles
+                .iter()
+                .filter_map(|triangle| ray_triangle(origin, direction, triangle, 500.))
+                .min_by(|a, b| a.total_cmp(b));
+            let hit = prepped.raycast(origin, direction, 500.);
+
+            match (hit, expected) {
+                (Some((distance, normal)), Some(expected)) => {
+                    assert!(
+                        (distance - expected).abs() < 0.001,
+                        "{distance} != {expected}"
+                    );
+                    assert!(normal.dot(direction) <= 0.);
+                }
+                (None, None) => {}
+                (hit, expected) => panic!("{origin}: {hit:?} != {expected:?}"),
+            }
+        });
+    }
+
+    // Corners, edges and diagonals between triangles shouldn't push a box sideways
+    #[test]
+    fn box_on_flat_mesh_pushes_up() {
+        let mesh = grid_mesh(8, 10., |_, _| 0.);
+        let prepped = PreppedTriMesh::new(
+            &mesh,
+            glam::Vec3::ZERO,
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        );
+
+        let shape = CollisionShape::Box {
+            half_width: 4.,
+            half_height: 4.,
+            half_depth: 4.,
+        };
+
+        [
+            glam::vec3(15., 0., 15.),
+            glam::vec3(20., 0., 20.),
+            glam::vec3(20., 0., 25.),
+            glam::vec3(23.3, 0., 27.1),
+            glam::vec3(2., 0., 2.),
+        ]
+        .into_iter()
+        .for_each(|position| {
+            let resting = PreppedCollisionShape::from_collision_shape(
+                &shape,
+                position + glam::vec3(0., 3.5, 0.),
+                glam::Quat::IDENTITY,
+                glam::Vec3::ONE,
+            );
+
+            let contact = prepped.contact(&resting).unwrap();
+            assert!(
+                contact.normal.abs_diff_eq(glam::Vec3::Y, 0.0001),
+                "{position}: {contact:?}"
+            );
+            assert!(
+                (contact.depth - 0.5).abs() < 0.001,
+                "{position}: {contact:?}"
+            );
+        });
+    }
+
+    #[test]
+    fn long_sweep_hits_flat_mesh() {
+        let mesh = grid_mesh(8, 10., |_, _| 0.);
+        let prepped = PreppedTriMesh::new(
+            &mesh,
+            glam::Vec3::ZERO,
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        );
+
+        let moving = Aabb::new(glam::vec3(39., 503., 39.), glam::vec3(41., 505., 41.));
+        let (time, normal) = prepped.sweep(&moving, glam::vec3(0., -1000., 0.)).unwrap();
+
+        assert!((time - 0.503).abs() < 0.001, "{time}");
+        assert!(normal.abs_diff_eq(glam::Vec3::Y, 0.0001), "{normal}");
+    }
+}
@@ -0,0 +1,230 @@
+//====================================================================
+
+use std::collections::HashMap;
+
+use hecs::{Entity, World};
+
+use super::{prep_entity_shape, CollisionDirection, CollisionHits};
+
+//====================================================================
+
+// How far to nudge a touching entity into the other to find where they meet
+const CONTACT_PROBE: f32 = 0.5;
+
+//====================================================================
+
+// Direction is as seen from entity, the same as it would be in its CollisionHits
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CollisionEvent {
+    Started {
+        entity: Entity,
+        other: Entity,
+        direction: CollisionDirection,
+        point: glam::Vec3,
+    },
+    Ended {
+        entity: Entity,
+        other: Entity,
+        direction: CollisionDirection,
+        point: glam::Vec3,
+    },
+}
+
+//====================================================================
+
+pub(super) fn update_collision_events(
+    world: &World,
+    touching: &mut HashMap<(Entity, Entity), (CollisionDirection, glam::Vec3)>,
+    events: &mut Vec<CollisionEvent>,
+) {
+    // Both sides of a pair may have recorded it, so key on the lower entity
+    let mut current = HashMap::<(Entity, Entity), CollisionDirection>::new();
+
+    world
+        .query::<&CollisionHits>()
+        .into_iter()
+        .for_each(|(entity, hits)| {
+            hits.hits.iter().for_each(|(other, direction)| {
+                let (key, direction) = match entity < *other {
+                    true => ((entity, *other), *direction),
+                    false => ((*other, entity), direction.flip()),
+                };
+
+                current.entry(key).or_insert(direction);
+            });
+        });
+
+    let mut started = current
+        .iter()
+        .filter(|(key, _)| !touching.contains_key(key))
+        .map(|(key, direction)| (*key, *direction))
+        .collect::<Vec<_>>();
+
+    let mut ended = touching
+        .iter()
+        .filter(|(key, _)| !current.contains_key(key))
+        .map(|(key, value)| (*key, *value))
+        .collect::<Vec<_>>();
+
+    started.sort_unstable_by_key(|(key, _)| *key);
+    ended.sort_unstable_by_key(|(key, _)| *key);
+
+    started
+        .into_iter()
+        .for_each(|((entity, other), direction)| {
+            let point = contact_point(world, entity, other, direction);
+            touching.insert((entity, other), (direction, point));
+
+            events.push(CollisionEvent::Started {
+                entity,
+                other,
+                direction,
+                point,
+            });
+        });
+
+    ended
+        .into_iter()
+        .for_each(|((entity, other), (direction, point))| {
+            touching.remove(&(entity, other));
+
+            events.push(CollisionEvent::Ended {
+                entity,
+                other,
+                direction,
+                point,
+            });
+        });
+
+    // Keep directions up to date for pairs that are still touching
+    current.into_iter().for_each(|(key, direction)| {
+        if let Some(value) = touching.get_mut(&key) {
+            value.0 = direction;
+        }
+    });
+}
+
+//====================================================================
+
+// Collision usually leaves entities just touching rather than overlapping, so
+// push the first slightly into the other to get a contact out of them.
+fn contact_point(
+    world: &World,
+    entity: Entity,
+    other: Entity,
+    direction: CollisionDirection,
+) -> glam::Vec3 {
+    let probe = -direction.normal() * CONTACT_PROBE;
+
+    let (entity_shape, other_shape) = match (
+        prep_entity_shape(world, entity, probe),
+        prep_entity_shape(world, other, glam::Vec3::ZERO),
+    ) {
+        (Some(entity_shape), Some(other_shape)) => (entity_shape, other_shape),
+        _ => return glam::Vec3::ZERO,
+    };
+
+    match entity_shape.contact(&other_shape) {
+        Some(contact) => contact.point,
+
+        // Fall back to the middle of where their bounds meet
+        None => {
+            let a = entity_shape.bounds();
+            let b = other_shape.bounds();
+
+            (a.min.max(b.min) + a.max.min(b.max)) * 0.5
+        }
+    }
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        tests::{spawn_floor, spawn_mover, TICK},
+        Gravity, PhysicsHandler, Velocity,
+    };
+
+    fn tick_events(physics: &mut PhysicsHandler, world: &mut World) -> Vec<CollisionEvent> {
+        physics.tick(world, TICK);
+        physics.drain_collision_events().collect()
+    }
+
+    // Drops a mover onto the floor and returns it once the collision has started
+    fn land(physics: &mut PhysicsHandler, world: &mut World) -> (Entity, Entity) {
+        let floor = spawn_floor(world);
+        let mover = spawn_mover(world, glam::vec3(0., 30., 0.), glam::Vec3::ZERO);
+        world
+            .insert_one(mover, Gravity(glam::vec3(0., -400., 0.)))
+            .unwrap();
+
+        let events = (0..60)
+            .flat_map(|_| tick_events(physics, world))
+            .collect::<Vec<_>>();
+
+        match events.as_slice() {
+            [CollisionEvent::Started {
+                entity,
+                other,
+                direction,
+                point,
+            }] => {
+                assert_eq!((*entity).min(*other), floor.min(mover));
+                assert_eq!((*entity).max(*other), floor.max(mover));
+
+                // Mover is on top of the floor
+                let expected = match *entity == mover {
+                    true => CollisionDirection::PosY,
+                    false => CollisionDirection::NegY,
+                };
+                assert_eq!(*direction, expected);
+                assert!(point.y.abs() < 1., "{point}");
+            }
+            _ => panic!("{events:?}"),
+        }
+
+        (floor, mover)
+    }
+
+    fn assert_ended(events: &[CollisionEvent], floor: Entity, mover: Entity) {
+        match events {
+            [CollisionEvent::Ended { entity, other, .. }] => {
+                assert_eq!((*entity).min(*other), floor.min(mover));
+                assert_eq!((*entity).max(*other), floor.max(mover));
+            }
+            _ => panic!("{events:?}"),
+        }
+    }
+
+    #[test]
+    fn started_while_touching_then_ended() {
+        let mut world = World::new();
+        let mut physics = PhysicsHandler::default();
+        let (floor, mover) = land(&mut physics, &mut world);
+
+        (0..10).for_each(|_| assert!(tick_events(&mut physics, &mut world).is_empty()));
+
+        world.get::<&mut Velocity>(mover).unwrap().0 = glam::vec3(0., 300., 0.);
+        assert_ended(&tick_events(&mut physics, &mut world), floor, mover);
+    }
+
+    #[test]
+    fn ended_on_despawn() {
+        let mut world = World::new();
+        let mut physics = PhysicsHandler::default();
+        let (floor, mover) = land(&mut physics, &mut world);
+
+        world.despawn(mover).unwrap();
+        assert_ended(&tick_events(&mut physics, &mut world), floor, mover);
+        assert!(tick_events(&mut physics, &mut world).is_empty());
+
+        let mut world = World::new();
+        let mut physics = PhysicsHandler::default();
+        let (floor, mover) = land(&mut physics, &mut world);
+
+        world.despawn(floor).unwrap();
+        assert_ended(&tick_events(&mut physics, &mut world), floor, mover);
+    }
+}
@@ -0,0 +1,90 @@
+//====================================================================
+
+use std::collections::HashSet;
+
+use hecs::{Entity, World};
+
+use super::{
+    prep_entity_shape, CollisionHits, CollisionShape, DynamicCollisionType, KinematicCollisionType,
+    StaticCollisionType, TriggerCollisionType,
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugColliderKind {
+    Static,
+    Kinematic,
+    Dynamic,
+    Trigger,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DebugLine {
+    pub start: glam::Vec3,
+    pub end: glam::Vec3,
+    pub kind: DebugColliderKind,
+
+    // Hit something last step, or something is inside it for triggers
+    pub touching: bool,
+}
+
+//====================================================================
+
+pub(super) fn collect_debug_lines(
+    world: &World,
+    trigger_overlaps: &HashSet<(Entity, Entity)>,
+) -> Vec<DebugLine> {
+    // Statics usually don't keep their own hits, so go by who was hit as well
+    let mut touching = world
+        .query::<&CollisionHits>()
+        .into_iter()
+        .flat_map(|(entity, hits)| {
+            hits.hits
+                .iter()
+                .flat_map(move |(other, _)| [entity, *other])
+        })
+        .collect::<HashSet<_>>();
+
+    touching.extend(trigger_overlaps.iter().map(|(trigger, _)| *trigger));
+
+    world
+        .query::<()>()
+        .with::<&CollisionShape>()
+        .into_iter()
+        .filter_map(|(entity, _)| {
+            let entity_ref = world.entity(entity).ok()?;
+
+            let kind = match (
+                entity_ref.has::<StaticCollisionType>(),
+                entity_ref.has::<KinematicCollisionType>(),
+                entity_ref.has::<TriggerCollisionType>(),
+                entity_ref.has::<DynamicCollisionType>(),
+            ) {
+                (true, _, _, _) => DebugColliderKind::Static,
+                (_, true, _, _) => DebugColliderKind::Kinematic,
+                (_, _, true, _) => DebugColliderKind::Trigger,
+                (_, _, _, true) => DebugColliderKind::Dynamic,
+                _ => return None,
+            };
+
+            let shape = prep_entity_shape(world, entity, glam::Vec3::ZERO)?;
+            let touching = touching.contains(&entity);
+
+            Some(
+                shape
+                    .wireframe()
+                    .into_iter()
+                    .map(move |(start, end)| DebugLine {
+                        start,
+                        end,
+                        kind,
+                        touching,
+                    }),
+            )
+        })
+        .flatten()
+        .collect()
+}
+
+//====================================================================
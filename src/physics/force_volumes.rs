@@ -0,0 +1,299 @@
+//====================================================================
+
+use std::collections::HashSet;
+
+use hecs::{Entity, World};
+
+use super::{prep_entity_shape, Gravity, TriggerEvent, Velocity};
+
+//====================================================================
+
+// Put on a trigger to push around the dynamic entities inside it
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForceVolume {
+    // Constant acceleration, per second
+    Wind(glam::Vec3),
+
+    // Lift is an upward acceleration scaled by how much of the entity is below the
+    // top of the volume. Drag is a per second rate, scaled the same way.
+    Buoyancy { lift: f32, drag: f32 },
+
+    // Replaces the Gravity of anything inside, which gets its own back on leaving
+    GravityOverride(glam::Vec3),
+
+    // Launches entities as they enter. Vertical speed is replaced, horizontal is added to.
+    JumpPad(glam::Vec3),
+}
+
+// Added to entities while a GravityOverride volume is changing their gravity
+struct GravityOverridden {
+    original: glam::Vec3,
+    volume: Entity,
+}
+
+//====================================================================
+
+pub(super) fn apply_force_volumes(
+    world: &mut World,
+    overlaps: &HashSet<(Entity, Entity)>,
+    events: &[TriggerEvent],
+    delta_time: f32,
+) {
+    let mut volumes = overlaps
+        .iter()
+        .filter_map(|(trigger, other)| {
+            let volume = *world.get::<&ForceVolume>(*trigger).ok()?;
+            Some((*trigger, *other, volume))
+        })
+        .collect::<Vec<_>>();
+
+    volumes.sort_unstable_by_key(|(trigger, other, _)| (*trigger, *other));
+
+    volumes
+        .iter()
+        .for_each(|(trigger, other, volume)| match volume {
+            ForceVolume::Wind(acceleration) => {
+                if let Ok(mut velocity) = world.get::<&mut Velocity>(*other) {
+                    velocity.0 += *acceleration * delta_time;
+                }
+            }
+
+            ForceVolume::Buoyancy { lift, drag } => {
+                let submerged = submerged_fraction(world, *trigger, *other);
+
+                if let Ok(mut velocity) = world.get::<&mut Velocity>(*other) {
+                    velocity.0.y += lift * submerged * delta_time;
+                    velocity.0 *= (-drag * submerged * delta_time).exp();
+                }
+            }
+
+            ForceVolume::GravityOverride(_) | ForceVolume::JumpPad(_) => {}
+        });
+
+    // Entity and the volume now deciding its gravity, if any
+    let mut gravity_changes = Vec::new();
+
+    events.iter().for_each(|event| match *event {
+        TriggerEvent::Entered { trigger, other } => {
+            let volume = match world.get::<&ForceVolume>(trigger) {
+                Ok(volume) => *volume,
+                Err(_) => return,
+            };
+
+            match volume {
+                ForceVolume::JumpPad(launch) => {
+                    if let Ok(mut velocity) = world.get::<&mut Velocity>(other) {
+                        velocity.0.x += launch.x;
+                        velocity.0.y = launch.y;
+                        velocity.0.z += launch.z;
+                    }
+                }
+
+                ForceVolume::GravityOverride(gravity) => {
+                    gravity_changes.push((other, Some((trigger, gravity))));
+                }
+
+                ForceVolume::Wind(_) | ForceVolume::Buoyancy { .. } => {}
+            }
+        }
+
+        // The volume may have been despawned so go by what the entity remembers
+        TriggerEvent::Exited { trigger, other } => {
+            let overridden_by = world
+                .get::<&GravityOverridden>(other)
+                .map(|overridden| overridden.volume)
+                .ok();
+
+            if overridden_by == Some(trigger) {
+                // Hand over to any other gravity volume it's still inside
+                let next = volumes
+                    .iter()
+                    .find_map(|(next, inside, volume)| match volume {
+                        ForceVolume::GravityOverride(gravity) if *inside == other => {
+                            Some((*next, *gravity))
+                        }
+                        _ => None,
+                    });
+
+                gravity_changes.push((other, next));
+            }
+        }
+
+        TriggerEvent::Stayed { .. } => {}
+    });
+
+    gravity_changes
+        .into_iter()
+        .for_each(|(entity, change)| update_gravity_override(world, entity, change));
+}
+
+//====================================================================
+
+fn update_gravity_override(
+    world: &mut World,
+    entity: Entity,
+    change: Option<(Entity, glam::Vec3)>,
+) {
+    let original = match world.get::<&Gravity>(entity) {
+        Ok(gravity) => gravity.0,
+        Err(_) => return,
+    };
+
+    let overridden = world
+        .get::<&GravityOverridden>(entity)
+        .map(|overridden| overridden.original)
+        .ok();
+
+    match (change, overridden) {
+        (Some((volume, gravity)), overridden) => {
+            let original = overridden.unwrap_or(original);
+
+            world.get::<&mut Gravity>(entity).unwrap().0 = gravity;
+            world
+                .insert_one(entity, GravityOverridden { original, volume })
+                .unwrap();
+        }
+
+        (None, Some(original)) => {
+            world.get::<&mut Gravity>(entity).unwrap().0 = original;
+            world.remove_one::<GravityOverridden>(entity).unwrap();
+        }
+
+        (None, None) => {}
+    }
+}
+
+// How much of an entity is below the top of a volume, 0 - 1
+fn submerged_fraction(world: &World, volume: Entity, entity: Entity) -> f32 {
+    let (volume, entity) = match (
+        prep_entity_shape(world, volume, glam::Vec3::ZERO),
+        prep_entity_shape(world, entity, glam::Vec3::ZERO),
+    ) {
+        (Some(volume), Some(entity)) => (volume.bounds(), entity.bounds()),
+        _ => return 0.,
+    };
+
+    let height = entity.max.y - entity.min.y;
+
+    match height > 0. {
+        true => ((volume.max.y - entity.min.y) / height).clamp(0., 1.),
+        false => 1.,
+    }
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use hecs_engine::common::Transform;
+
+    use super::*;
+    use crate::physics::{
+        tests::{cube, run, spawn_mover, translation},
+        PhysicsHandler, TriggerCollisionType,
+    };
+
+    fn spawn_volume(world: &mut World, translation: glam::Vec3, volume: ForceVolume) -> Entity {
+        world.spawn((
+            Transform::from_translation(translation),
+            TriggerCollisionType,
+            cube(50.),
+            volume,
+        ))
+    }
+
+    #[inline]
+    fn velocity(world: &World, entity: Entity) -> glam::Vec3 {
+        world.get::<&Velocity>(entity).unwrap().0
+    }
+
+    #[test]
+    fn wind_pushes_only_inside() {
+        let mut world = World::new();
+        spawn_volume(
+            &mut world,
+            glam::Vec3::ZERO,
+            ForceVolume::Wind(glam::vec3(300., 0., 0.)),
+        );
+        let inside = spawn_mover(&mut world, glam::Vec3::ZERO, glam::Vec3::ZERO);
+        let outside = spawn_mover(&mut world, glam::vec3(0., 0., 200.), glam::Vec3::ZERO);
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 10);
+
+        assert!(velocity(&world, inside).x > 0.);
+        assert_eq!(velocity(&world, outside), glam::Vec3::ZERO);
+    }
+
+    #[test]
+    fn buoyancy_lifts_towards_surface() {
+        let mut world = World::new();
+        spawn_volume(
+            &mut world,
+            glam::Vec3::ZERO,
+            ForceVolume::Buoyancy {
+                lift: 800.,
+                drag: 2.,
+            },
+        );
+        let floater = spawn_mover(&mut world, glam::vec3(0., -40., 0.), glam::Vec3::ZERO);
+        world
+            .insert_one(floater, Gravity(glam::vec3(0., -400., 0.)))
+            .unwrap();
+
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, &mut world, 300);
+
+        // Lift is scaled down as it comes out of the top, so it settles half out
+        let y = translation(&world, floater).y;
+        assert!((y - 50.).abs() < 5., "{y}");
+    }
+
+    #[test]
+    fn gravity_override_until_exit() {
+        let mut world = World::new();
+        spawn_volume(
+            &mut world,
+            glam::vec3(100., 0., 0.),
+            ForceVolume::GravityOverride(glam::vec3(0., 100., 0.)),
+        );
+        let mover = spawn_mover(&mut world, glam::Vec3::ZERO, glam::vec3(600., 0., 0.));
+        world
+            .insert_one(mover, Gravity(glam::vec3(0., -400., 0.)))
+            .unwrap();
+
+        let gravity = |world: &World| world.get::<&Gravity>(mover).unwrap().0;
+        let mut physics = PhysicsHandler::default();
+
+        run(&mut physics, &mut world, 10);
+        assert_eq!(gravity(&world), glam::vec3(0., 100., 0.));
+
+        run(&mut physics, &mut world, 20);
+        assert!(translation(&world, mover).x > 160.);
+        assert_eq!(gravity(&world), glam::vec3(0., -400., 0.));
+    }
+
+    #[test]
+    fn jump_pad_launches_on_entry() {
+        let mut world = World::new();
+        spawn_volume(
+            &mut world,
+            glam::Vec3::ZERO,
+            ForceVolume::JumpPad(glam::vec3(50., 500., 0.)),
+        );
+        let mover = spawn_mover(
+            &mut world,
+            glam::vec3(0., 80., 0.),
+            glam::vec3(0., -300., 0.),
+        );
+
+        let mut physics = PhysicsHandler::default();
+        let launched = (0..30).any(|_| {
+            run(&mut physics, &mut world, 1);
+            velocity(&world, mover) == glam::vec3(50., 500., 0.)
+        });
+
+        assert!(launched);
+    }
+}
@@ -0,0 +1,480 @@
+//====================================================================
+
+use hecs::{Entity, World};
+use hecs_engine::common::Transform;
+
+use super::{
+    broadphase::{Aabb, SpatialGrid},
+    collision::PreppedCollisionShape,
+    prep_dynamic_shape, prep_rigid_body_shape, world_transform, CollisionLayers, CollisionShape,
+    DynamicCollisionType, RigidBody, StaticCollisionType, TriggerCollisionType,
+};
+
+//====================================================================
+
+const SHAPE_CAST_STEPS: u32 = 12;
+
+//====================================================================
+
+// Layers are the collision layer memberships a query hits, same as a CollisionLayers filter
+#[derive(Debug, Clone)]
+pub struct QueryFilter {
+    pub statics: bool,
+    pub dynamics: bool,
+    pub triggers: bool,
+    pub layers: u32,
+    pub exclude: Vec<Entity>,
+}
+
+impl Default for QueryFilter {
+    fn default() -> Self {
+        Self {
+            statics: true,
+            dynamics: true,
+            triggers: false,
+            layers: u32::MAX,
+            exclude: Vec::new(),
+        }
+    }
+}
+
+impl QueryFilter {
+    #[inline]
+    fn allows(&self, world: &World, entity: Entity) -> bool {
+        let membership = world
+            .get::<&CollisionLayers>(entity)
+            .map(|layers| layers.membership)
+            .unwrap_or(u32::MAX);
+
+        membership & self.layers != 0 && !self.exclude.contains(&entity)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QueryHit {
+    pub entity: Entity,
+    pub distance: f32,
+    pub point: glam::Vec3,
+    pub normal: glam::Vec3,
+}
+
+//====================================================================
+
+pub(super) fn raycast(
+    world: &World,
+    grid: &SpatialGrid,
+    origin: glam::Vec3,
+    direction: glam::Vec3,
+    max_distance: f32,
+    filter: &QueryFilter,
+) -> Option<QueryHit> {
+    let direction = direction.normalize_or_zero();
+
+    if direction == glam::Vec3::ZERO {
+        return None;
+    }
+
+    let bounds = Aabb::new(
+        origin.min(origin + direction * max_distance),
+        origin.max(origin + direction * max_distance),
+    );
+
+    let statics = match filter.statics {
+        true => grid.query_ray(origin, direction, max_distance),
+        false => Vec::new(),
+    };
+
+    let mut closest = None;
+
+    let mut test = |entity: Entity, shape: &PreppedCollisionShape| {
+        let limit = closest
+            .as_ref()
+            .map(|hit: &QueryHit| hit.distance)
+            .unwrap_or(max_distance);
+
+        if let Some((distance, normal)) = shape.raycast(origin, direction, limit) {
+            closest = Some(QueryHit {
+                entity,
+                distance,
+                point: origin + direction * distance,
+                normal,
+            });
+        }
+    };
+
+    statics
+        .into_iter()
+        .filter(|entity| filter.allows(world, *entity))
+        .for_each(|entity| {
+            if let Ok(shape) = world.get::<&PreppedCollisionShape>(entity) {
+                test(entity, &shape);
+            }
+        });
+
+    moving_shapes(world, &bounds, filter)
+        .into_iter()
+        .for_each(|(entity, shape)| test(entity, &shape));
+
+    closest
+}
+
+// Sweeps an axis aligned shape from one point to another and returns the first
+// thing it would touch. The distance is how far the shape can travel before it does.
+pub(super) fn shape_cast(
+    world: &World,
+    grid: &SpatialGrid,
+    shape: &CollisionShape,
+    from: glam::Vec3,
+    to: glam::Vec3,
+    filter: &QueryFilter,
+) -> Option<QueryHit> {
+    let prep_at = |translation: glam::Vec3| {
+        PreppedCollisionShape::from_collision_shape(
+            shape,
+            translation,
+            glam::Quat::IDENTITY,
+            glam::Vec3::ONE,
+        )
+    };
+
+    let start = prep_at(from).bounds();
+    let end = prep_at(to).bounds();
+    let bounds = start.union(&end);
+
+    let thickness = (start.max - start.min).min_element().max(f32::EPSILON);
+    let length = from.distance(to);
+
+    // Furthest free travel, first blocked travel and what blocked it
+    let mut closest: Option<(f32, f32, Entity)> = None;
+
+    let mut test = |entity: Entity, other: &PreppedCollisionShape| {
+        let limit = closest.map(|(travel, _, _)| travel).unwrap_or(1.);
+        let collides_at = |travel: f32| prep_at(from.lerp(to, travel)).check_collision(other);
+
+        // Only the part of the cast where the bounds overlap needs checking
+        let (enter, exit) = match start.overlap_window(to - from, &other.bounds()) {
+            Some((enter, exit)) if enter <= limit => (enter, exit.min(limit)),
+            _ => return,
+        };
+
+        // Sample no further apart than the shape is thick so nothing can be skipped over
+        let samples = (((exit - enter) * length / thickness).ceil() as u32).max(1);
+        let spacing = (exit - enter) / samples as f32;
+
+        let blocked = (0..=samples)
+            .map(|sample| enter + sample as f32 * spacing)
+            .find(|travel| collides_at(*travel));
+
+        let mut blocked = match blocked {
+            Some(0.) => {
+                closest = Some((0., 0., entity));
+                return;
+            }
+            Some(blocked) => blocked,
+            None => return,
+        };
+
+        let mut free = (blocked - spacing).max(0.);
+
+        for _ in 0..SHAPE_CAST_STEPS {
+            let mid = (free + blocked) * 0.5;
+
+            match collides_at(mid) {
+                true => blocked = mid,
+                false => free = mid,
+            }
+        }
+
+        if free < limit {
+            closest = Some((free, blocked, entity));
+        }
+    };
+
+    grid.query(&bounds)
+        .into_iter()
+        .filter(|entity| filter.statics && filter.allows(world, *entity))
+        .for_each(|entity| {
+            if let Ok(other) = world.get::<&PreppedCollisionShape>(entity) {
+                test(entity, &other);
+            }
+        });
+
+    let moving = moving_shapes(world, &bounds, filter);
+
+    moving
+        .iter()
+        .for_each(|(entity, other)| test(*entity, other));
+
+    let (travel, blocked, entity) = closest?;
+    let touching = prep_at(from.lerp(to, blocked));
+
+    let contact = match moving.iter().find(|(other, _)| *other == entity) {
+        Some((_, other)) => touching.contact(other),
+        None => world
+            .get::<&PreppedCollisionShape>(entity)
+            .ok()
+            .and_then(|other| touching.contact(&other)),
+    };
+
+    let (point, normal) = match contact {
+        Some(contact) => (contact.point, contact.normal),
+        None => (from.lerp(to, travel), (from - to).normalize_or_zero()),
+    };
+
+    Some(QueryHit {
+        entity,
+        distance: from.distance(to) * travel,
+        point,
+        normal,
+    })
+}
+
+// Everything an axis aligned shape placed at translation would be overlapping, for
+// checking there's room before putting something somewhere
+pub(super) fn overlap(
+    world: &World,
+    grid: &SpatialGrid,
+    shape: &CollisionShape,
+    translation: glam::Vec3,
+    filter: &QueryFilter,
+) -> Vec<Entity> {
+    let prepped = PreppedCollisionShape::from_collision_shape(
+        shape,
+        translation,
+        glam::Quat::IDENTITY,
+        glam::Vec3::ONE,
+    );
+    let bounds = prepped.bounds();
+
+    let mut hits = grid
+        .query(&bounds)
+        .into_iter()
+        .filter(|entity| filter.statics && filter.allows(world, *entity))
+        .filter(|entity| {
+            world
+                .get::<&PreppedCollisionShape>(*entity)
+                .map(|other| prepped.check_collision(&other))
+                .unwrap_or(false)
+        })
+        .collect::<Vec<_>>();
+
+    hits.extend(
+        moving_shapes(world, &bounds, filter)
+            .into_iter()
+            .filter(|(_, other)| prepped.check_collision(other))
+            .map(|(entity, _)| entity),
+    );
+
+    hits
+}
+
+//====================================================================
+
+// Dynamics and triggers aren't kept prepped between ticks so prep any near the query here
+fn moving_shapes(
+    world: &World,
+    bounds: &Aabb,
+    filter: &QueryFilter,
+) -> Vec<(Entity, PreppedCollisionShape)> {
+    let mut shapes = Vec::new();
+
+    if filter.dynamics {
+        world
+            .query::<(&Transform, &CollisionShape, Option<&RigidBody>)>()
+            .with::<&DynamicCollisionType>()
+            .without::<&StaticCollisionType>()
+            .without::<&TriggerCollisionType>()
+            .into_iter()
+            .filter(|(entity, _)| filter.allows(world, *entity))
+            .for_each(|(entity, (transform, shape, rigid_body))| {
+                let prepped = match rigid_body {
+                    Some(_) => prep_rigid_body_shape(shape, transform),
+                    None => prep_dynamic_shape(shape, transform.translation, transform),
+                };
+
+                if prepped.bounds().intersects(bounds) {
+                    shapes.push((entity, prepped));
+                }
+            });
+    }
+
+    if filter.triggers {
+        world
+            .query::<&CollisionShape>()
+            .with::<&TriggerCollisionType>()
+            .into_iter()
+            .filter(|(entity, _)| filter.allows(world, *entity))
+            .for_each(|(entity, shape)| {
+                let transform = match world_transform(world, entity) {
+                    Some(transform) => transform,
+                    None => return,
+                };
+
+                let prepped = PreppedCollisionShape::from_collision_shape(
+                    shape,
+                    transform.translation,
+                    transform.rotation,
+                    transform.scale,
+                );
+
+                if prepped.bounds().intersects(bounds) {
+                    shapes.push((entity, prepped));
+                }
+            });
+    }
+
+    shapes
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        tests::{cube, run, spawn_static},
+        CharacterCollisionBundle, PhysicsHandler,
+    };
+
+    // Ticked once so the statics are prepped and in the grid
+    fn ticked(world: &mut World) -> PhysicsHandler {
+        let mut physics = PhysicsHandler::default();
+        run(&mut physics, world, 1);
+        physics
+    }
+
+    #[test]
+    fn raycast_hits_closest_allowed() {
+        let mut world = World::new();
+        let near = spawn_static(&mut world, glam::vec3(100., 0., 0.), cube(10.));
+        let far = spawn_static(&mut world, glam::vec3(200., 0., 0.), cube(10.));
+
+        let dynamic = world.spawn(CharacterCollisionBundle::from_shape(cube(5.)));
+        world
+            .insert_one(
+                dynamic,
+                Transform::from_translation(glam::vec3(50., 0., 0.)),
+            )
+            .unwrap();
+
+        let physics = ticked(&mut world);
+        let cast = |filter: &QueryFilter| {
+            raycast(
+                &world,
+                &physics.static_grid,
+                glam::Vec3::ZERO,
+                glam::Vec3::X,
+                1000.,
+                filter,
+            )
+        };
+
+        let hit = cast(&QueryFilter::default()).unwrap();
+        assert_eq!(hit.entity, dynamic);
+        assert!((hit.distance - 45.).abs() < 0.01, "{}", hit.distance);
+
+        let statics_only = QueryFilter {
+            dynamics: false,
+            ..Default::default()
+        };
+        let hit = cast(&statics_only).unwrap();
+        assert_eq!(hit.entity, near);
+        assert!((hit.distance - 90.).abs() < 0.01, "{}", hit.distance);
+        assert!(hit.normal.abs_diff_eq(-glam::Vec3::X, 0.001));
+
+        let hit = cast(&QueryFilter {
+            exclude: vec![near],
+            ..statics_only.clone()
+        })
+        .unwrap();
+        assert_eq!(hit.entity, far);
+
+        assert!(cast(&QueryFilter {
+            exclude: vec![near, far],
+            ..statics_only
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn shape_cast_stops_touching() {
+        let mut world = World::new();
+        let wall = spawn_static(&mut world, glam::vec3(100., 0., 0.), cube(10.));
+        let physics = ticked(&mut world);
+
+        let hit = shape_cast(
+            &world,
+            &physics.static_grid,
+            &cube(5.),
+            glam::Vec3::ZERO,
+            glam::vec3(200., 0., 0.),
+            &QueryFilter::default(),
+        )
+        .unwrap();
+
+        assert_eq!(hit.entity, wall);
+        assert!((hit.distance - 85.).abs() < 0.1, "{}", hit.distance);
+        assert!(hit.normal.abs_diff_eq(-glam::Vec3::X, 0.001));
+
+        // Stopping short of the wall misses it
+        assert!(shape_cast(
+            &world,
+            &physics.static_grid,
+            &cube(5.),
+            glam::Vec3::ZERO,
+            glam::vec3(80., 0., 0.),
+            &QueryFilter::default(),
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn overlap_filters() {
+        let mut world = World::new();
+        let wall = spawn_static(&mut world, glam::Vec3::ZERO, cube(10.));
+        let layered = spawn_static(&mut world, glam::vec3(30., 0., 0.), cube(10.));
+        world
+            .insert_one(layered, CollisionLayers::new(0b10, u32::MAX))
+            .unwrap();
+        let trigger = world.spawn((
+            Transform::from_translation(glam::vec3(0., 15., 0.)),
+            TriggerCollisionType,
+            cube(10.),
+        ));
+
+        let physics = ticked(&mut world);
+        let overlapping = |filter: &QueryFilter| {
+            let mut hits = overlap(
+                &world,
+                &physics.static_grid,
+                &cube(10.),
+                glam::vec3(15., 10., 0.),
+                filter,
+            );
+            hits.sort();
+            hits
+        };
+
+        let mut expected = vec![wall, layered];
+        expected.sort();
+        assert_eq!(overlapping(&QueryFilter::default()), expected);
+
+        assert_eq!(
+            overlapping(&QueryFilter {
+                layers: 0b01,
+                ..Default::default()
+            }),
+            vec![wall]
+        );
+
+        let mut expected = vec![wall, layered, trigger];
+        expected.sort();
+        assert_eq!(
+            overlapping(&QueryFilter {
+                triggers: true,
+                ..Default::default()
+            }),
+            expected
+        );
+    }
+}
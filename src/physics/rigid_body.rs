@@ -0,0 +1,559 @@
+//====================================================================
+
+use hecs::{Entity, World};
+use hecs_engine::common::Transform;
+
+use super::{
+    broadphase::SpatialGrid, collision::PreppedCollisionShape, prep_dynamic_shape,
+    prep_rigid_body_shape, AngularVelocity, CollisionDirection, CollisionHits, CollisionLayers,
+    CollisionShape, DynamicCollisionType, Friction, Mass, Restitution, RigidBody,
+    StaticCollisionType, StaticQuery, TriggerCollisionType, Velocity,
+};
+
+//====================================================================
+
+const SOLVER_ITERATIONS: u32 = 8;
+
+// Slower impacts than this don't bounce so resting contacts settle instead of jittering
+const RESTING_SPEED: f32 = 20.;
+
+// Penetration allowed before pushing apart, and how much of the rest is fixed per step
+const PENETRATION_SLOP: f32 = 0.05;
+const CORRECTION_FACTOR: f32 = 0.6;
+
+const DEFAULT_FRICTION: f32 = 0.5;
+
+//====================================================================
+
+struct Body {
+    entity: Entity,
+    shape: PreppedCollisionShape,
+    layers: CollisionLayers,
+
+    position: glam::Vec3,
+    rotation: glam::Quat,
+    velocity: glam::Vec3,
+    angular_velocity: glam::Vec3,
+
+    inverse_mass: f32,
+    inverse_inertia: glam::Mat3,
+    restitution: f32,
+    friction: f32,
+}
+
+impl Body {
+    #[inline]
+    fn velocity_at(&self, offset: glam::Vec3) -> glam::Vec3 {
+        self.velocity + self.angular_velocity.cross(offset)
+    }
+
+    #[inline]
+    fn apply_impulse(&mut self, impulse: glam::Vec3, offset: glam::Vec3) {
+        self.velocity += impulse * self.inverse_mass;
+        self.angular_velocity += self.inverse_inertia * offset.cross(impulse);
+    }
+
+    // How much an impulse along the direction at the offset changes velocity there
+    #[inline]
+    fn inverse_effective_mass(&self, direction: glam::Vec3, offset: glam::Vec3) -> f32 {
+        let angular = (self.inverse_inertia * offset.cross(direction)).cross(offset);
+        self.inverse_mass + direction.dot(angular)
+    }
+}
+
+// Statics, kinematics and characters can't be moved by rigid bodies
+enum Other {
+    Body(usize),
+    Immovable {
+        entity: Entity,
+        velocity: glam::Vec3,
+        restitution: f32,
+        friction: f32,
+    },
+}
+
+struct RigidContact {
+    body: usize,
+    other: Other,
+
+    // Points from other towards body
+    normal: glam::Vec3,
+    point: glam::Vec3,
+    depth: f32,
+
+    restitution: f32,
+    friction: f32,
+    bounce: f32,
+    normal_impulse: f32,
+}
+
+//====================================================================
+
+pub(super) fn step_rigid_bodies(world: &World, grid: &SpatialGrid, delta_time: f32) {
+    let mut bodies = collect_bodies(world);
+
+    if bodies.is_empty() {
+        return;
+    }
+
+    let mut contacts = find_contacts(world, grid, &bodies, delta_time);
+
+    contacts.iter_mut().for_each(|contact| {
+        let approach = -relative_normal_speed(&bodies, contact);
+
+        contact.bounce = match approach > RESTING_SPEED {
+            true => approach * contact.restitution,
+            false => 0.,
+        };
+    });
+
+    for _ in 0..SOLVER_ITERATIONS {
+        contacts
+            .iter_mut()
+            .for_each(|contact| solve_contact(&mut bodies, contact));
+    }
+
+    bodies.iter_mut().for_each(|body| {
+        body.position += body.velocity * delta_time;
+        body.rotation = (glam::Quat::from_scaled_axis(body.angular_velocity * delta_time)
+            * body.rotation)
+            .normalize();
+    });
+
+    correct_positions(&mut bodies, &contacts);
+    record_hits(world, &bodies, &contacts);
+
+    bodies.into_iter().for_each(|body| {
+        let mut transform = world.get::<&mut Transform>(body.entity).unwrap();
+        transform.translation = body.position;
+        transform.rotation = body.rotation;
+
+        world.get::<&mut Velocity>(body.entity).unwrap().0 = body.velocity;
+        world.get::<&mut AngularVelocity>(body.entity).unwrap().0 = body.angular_velocity;
+    });
+}
+
+//====================================================================
+
+fn collect_bodies(world: &World) -> Vec<Body> {
+    world
+        .query::<(
+            &Transform,
+            &CollisionShape,
+            &Velocity,
+            &AngularVelocity,
+            Option<&Mass>,
+            Option<&Restitution>,
+            Option<&Friction>,
+            Option<&CollisionLayers>,
+        )>()
+        .with::<(&RigidBody, &DynamicCollisionType)>()
+        .into_iter()
+        .map(
+            |(
+                entity,
+                (transform, shape, velocity, angular_velocity, mass, restitution, friction, layers),
+            )| {
+                let mass = mass.map(|mass| mass.0).unwrap_or(1.);
+                let inverse_mass = match mass > 0. {
+                    true => 1. / mass,
+                    false => 0.,
+                };
+
+                let rotation = glam::Mat3::from_quat(transform.rotation);
+                let inverse_inertia = rotation
+                    * glam::Mat3::from_diagonal(inverse_inertia(shape, transform.scale, mass))
+                    * rotation.transpose();
+
+                Body {
+                    entity,
+                    shape: prep_rigid_body_shape(shape, transform),
+                    layers: layers.copied().unwrap_or_default(),
+
+                    position: transform.translation,
+                    rotation: transform.rotation,
+                    velocity: velocity.0,
+                    angular_velocity: angular_velocity.0,
+
+                    inverse_mass,
+                    inverse_inertia,
+                    restitution: restitution.map(|restitution| restitution.0).unwrap_or(0.),
+                    friction: friction
+                        .map(|friction| friction.0)
+                        .unwrap_or(DEFAULT_FRICTION),
+                }
+            },
+        )
+        .collect()
+}
+
+// Local inverse inertia of a solid shape. Capsules are treated as their bounding box.
+fn inverse_inertia(shape: &CollisionShape, scale: glam::Vec3, mass: f32) -> glam::Vec3 {
+    if mass <= 0. {
+        return glam::Vec3::ZERO;
+    }
+
+    match inertia(shape, scale, mass) {
+        Some(inertia) => inertia.max(glam::Vec3::splat(f32::EPSILON)).recip(),
+        None => glam::Vec3::ZERO,
+    }
+}
+
+// None for shapes that can't spin
+fn inertia(shape: &CollisionShape, scale: glam::Vec3, mass: f32) -> Option<glam::Vec3> {
+    let inertia = match shape {
+        CollisionShape::Box {
+            half_width,
+            half_height,
+            half_depth,
+        } => {
+            let squared = (glam::vec3(*half_width, *half_height, *half_depth) * scale).powf(2.);
+
+            glam::vec3(
+                squared.y + squared.z,
+                squared.x + squared.z,
+                squared.x + squared.y,
+            ) * mass
+                / 3.
+        }
+
+        CollisionShape::Sphere { radius } => {
+            let radius = radius * scale.max_element();
+            glam::Vec3::splat(0.4 * mass * radius * radius)
+        }
+
+        CollisionShape::Capsule {
+            radius,
+            half_height,
+        } => {
+            let radius = radius * scale.x.max(scale.z);
+            let squared = glam::vec3(radius, half_height * scale.y, radius).powf(2.);
+
+            glam::vec3(
+                squared.y + squared.z,
+                squared.x + squared.z,
+                squared.x + squared.y,
+            ) * mass
+                / 3.
+        }
+
+        // Meshes and terrain are static only and never spin
+        CollisionShape::TriMesh(_) | CollisionShape::Heightfield(_) => return None,
+
+        // Mass is split evenly between the parts, each moved out to its offset
+        CollisionShape::Compound(parts) => {
+            let part_mass = mass / parts.len().max(1) as f32;
+
+            return parts
+                .iter()
+                .filter_map(|(offset, part)| {
+                    let offset = *offset * scale;
+                    let squared = offset * offset;
+                    let parallel = glam::vec3(
+                        squared.y + squared.z,
+                        squared.x + squared.z,
+                        squared.x + squared.y,
+                    ) * part_mass;
+
+                    inertia(part, scale, part_mass).map(|inertia| inertia + parallel)
+                })
+                .reduce(|a, b| a + b);
+        }
+    };
+
+    Some(inertia)
+}
+
+fn find_contacts(
+    world: &World,
+    grid: &SpatialGrid,
+    bodies: &[Body],
+    delta_time: f32,
+) -> Vec<RigidContact> {
+    let mut contacts = Vec::new();
+
+    let characters = world
+        .query::<(&Transform, &CollisionShape, Option<&Velocity>)>()
+        .with::<&DynamicCollisionType>()
+        .without::<&StaticCollisionType>()
+        .without::<&TriggerCollisionType>()
+        .without::<&RigidBody>()
+        .into_iter()
+        .map(|(entity, (transform, shape, velocity))| {
+            let velocity = velocity.map(|velocity| velocity.0).unwrap_or_default();
+
+            // Characters are stopped by rigid bodies before reaching them, so push bodies
+            // out of where they're heading rather than where they ended up
+            (
+                entity,
+                prep_dynamic_shape(
+                    shape,
+                    transform.translation + velocity * delta_time,
+                    transform,
+                ),
+                velocity,
+                CollisionLayers::of(world, entity),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    bodies.iter().enumerate().for_each(|(index, body)| {
+        let bounds = body.shape.bounds();
+
+        let statics = StaticQuery {
+            world,
+            grid,
+            bodies: &[],
+            layers: body.layers,
+        };
+
+        statics
+            .query(&bounds)
+            .into_iter()
+            .for_each(|static_entity| {
+                let static_shape = world.get::<&PreppedCollisionShape>(static_entity).unwrap();
+
+                if let Some(contact) = body.shape.contact(&static_shape) {
+                    let friction = world
+                        .get::<&Friction>(static_entity)
+                        .map(|friction| friction.0)
+                        .unwrap_or(DEFAULT_FRICTION);
+
+                    let restitution = world
+                        .get::<&Restitution>(static_entity)
+                        .map(|restitution| restitution.0)
+                        .unwrap_or(0.);
+
+                    contacts.push(RigidContact::new(
+                        body,
+                        index,
+                        Other::Immovable {
+                            entity: static_entity,
+                            velocity: glam::Vec3::ZERO,
+                            restitution,
+                            friction,
+                        },
+                        contact.normal,
+                        contact.point,
+                        contact.depth,
+                    ));
+                }
+            });
+
+        characters
+            .iter()
+            .filter(|(_, shape, _, layers)| {
+                body.layers.interacts_with(layers) && bounds.intersects(&shape.bounds())
+            })
+            .for_each(|(entity, shape, velocity, _)| {
+                if let Some(contact) = body.shape.contact(shape) {
+                    // Only the part that's really overlapping needs pushing out directly,
+                    // the rest is covered by matching the character's velocity
+                    let heading = velocity.dot(contact.normal).max(0.) * delta_time;
+
+                    contacts.push(RigidContact::new(
+                        body,
+                        index,
+                        Other::Immovable {
+                            entity: *entity,
+                            velocity: *velocity,
+                            restitution: 0.,
+                            friction: DEFAULT_FRICTION,
+                        },
+                        contact.normal,
+                        contact.point,
+                        (contact.depth - heading).max(0.),
+                    ));
+                }
+            });
+
+        bodies
+            .iter()
+            .enumerate()
+            .skip(index + 1)
+            .filter(|(_, other)| {
+                body.layers.interacts_with(&other.layers)
+                    && bounds.intersects(&other.shape.bounds())
+            })
+            .for_each(|(other_index, other)| {
+                if let Some(contact) = body.shape.contact(&other.shape) {
+                    contacts.push(RigidContact::new(
+                        body,
+                        index,
+                        Other::Body(other_index),
+                        contact.normal,
+                        contact.point,
+                        contact.depth,
+                    ));
+                }
+            });
+    });
+
+    contacts
+}
+
+impl RigidContact {
+    fn new(
+        body: &Body,
+        index: usize,
+        other: Other,
+        normal: glam::Vec3,
+        point: glam::Vec3,
+        depth: f32,
+    ) -> Self {
+        let (other_restitution, other_friction) = match &other {
+            Other::Body(_) => (body.restitution, body.friction),
+            Other::Immovable {
+                restitution,
+                friction,
+                ..
+            } => (*restitution, *friction),
+        };
+
+        Self {
+            body: index,
+            other,
+            normal,
+            point,
+            depth,
+            restitution: body.restitution.max(other_restitution),
+            friction: (body.friction * other_friction).sqrt(),
+            bounce: 0.,
+            normal_impulse: 0.,
+        }
+    }
+}
+
+//====================================================================
+
+#[inline]
+fn relative_velocity(bodies: &[Body], contact: &RigidContact) -> glam::Vec3 {
+    let body = &bodies[contact.body];
+    let body_velocity = body.velocity_at(contact.point - body.position);
+
+    let other_velocity = match &contact.other {
+        Other::Body(other) => {
+            let other = &bodies[*other];
+            other.velocity_at(contact.point - other.position)
+        }
+        Other::Immovable { velocity, .. } => *velocity,
+    };
+
+    body_velocity - other_velocity
+}
+
+#[inline]
+fn relative_normal_speed(bodies: &[Body], contact: &RigidContact) -> f32 {
+    relative_velocity(bodies, contact).dot(contact.normal)
+}
+
+fn solve_contact(bodies: &mut [Body], contact: &mut RigidContact) {
+    let body_offset = contact.point - bodies[contact.body].position;
+    let other_offset = match contact.other {
+        Other::Body(other) => Some((other, contact.point - bodies[other].position)),
+        Other::Immovable { .. } => None,
+    };
+
+    let inverse_effective_mass = |bodies: &[Body], direction: glam::Vec3| {
+        bodies[contact.body].inverse_effective_mass(direction, body_offset)
+            + match other_offset {
+                Some((other, offset)) => bodies[other].inverse_effective_mass(direction, offset),
+                None => 0.,
+            }
+    };
+
+    let apply = |bodies: &mut [Body], impulse: glam::Vec3| {
+        bodies[contact.body].apply_impulse(impulse, body_offset);
+
+        if let Some((other, offset)) = other_offset {
+            bodies[other].apply_impulse(-impulse, offset);
+        }
+    };
+
+    // Normal impulse, accumulated so later iterations can take back what earlier ones overdid
+    let normal_mass = inverse_effective_mass(bodies, contact.normal);
+
+    if normal_mass <= f32::EPSILON {
+        return;
+    }
+
+    let normal_speed = relative_normal_speed(bodies, contact);
+    let impulse = (contact.bounce - normal_speed) / normal_mass;
+
+    let accumulated = (contact.normal_impulse + impulse).max(0.);
+    let impulse = accumulated - contact.normal_impulse;
+    contact.normal_impulse = accumulated;
+
+    apply(bodies, contact.normal * impulse);
+
+    // Friction opposes sliding, limited by how hard the contact is pressing
+    let velocity = relative_velocity(bodies, contact);
+    let tangent = velocity - contact.normal * velocity.dot(contact.normal);
+
+    if tangent.length_squared() <= f32::EPSILON {
+        return;
+    }
+
+    let tangent = tangent.normalize();
+    let tangent_mass = inverse_effective_mass(bodies, tangent);
+
+    if tangent_mass <= f32::EPSILON {
+        return;
+    }
+
+    let limit = contact.friction * contact.normal_impulse;
+    let impulse = (-velocity.dot(tangent) / tangent_mass).clamp(-limit, limit);
+
+    apply(bodies, tangent * impulse);
+}
+
+// Velocities alone let resting bodies slowly sink, so push overlaps apart directly
+fn correct_positions(bodies: &mut [Body], contacts: &[RigidContact]) {
+    contacts.iter().for_each(|contact| {
+        let other = match contact.other {
+            Other::Body(other) => Some(other),
+            Other::Immovable { .. } => None,
+        };
+
+        let total_inverse_mass = bodies[contact.body].inverse_mass
+            + other.map(|other| bodies[other].inverse_mass).unwrap_or(0.);
+
+        if total_inverse_mass <= 0. {
+            return;
+        }
+
+        let correction =
+            contact.normal * (contact.depth - PENETRATION_SLOP).max(0.) * CORRECTION_FACTOR
+                / total_inverse_mass;
+
+        let body = &mut bodies[contact.body];
+        body.position += correction * body.inverse_mass;
+
+        if let Some(other) = other {
+            let other = &mut bodies[other];
+            other.position -= correction * other.inverse_mass;
+        }
+    });
+}
+
+fn record_hits(world: &World, bodies: &[Body], contacts: &[RigidContact]) {
+    contacts.iter().for_each(|contact| {
+        let body = bodies[contact.body].entity;
+        let other = match contact.other {
+            Other::Body(other) => bodies[other].entity,
+            Other::Immovable { entity, .. } => entity,
+        };
+
+        let direction = CollisionDirection::from_normal(contact.normal);
+
+        if let Ok(mut hits) = world.get::<&mut CollisionHits>(body) {
+            hits.hits.push((other, direction));
+        }
+
+        if let Ok(mut hits) = world.get::<&mut CollisionHits>(other) {
+            hits.hits.push((body, direction.flip()));
+        }
+    });
+}
+
+//====================================================================
@@ -0,0 +1,174 @@
+//====================================================================
+
+use std::collections::HashSet;
+
+use hecs::{Entity, World};
+use hecs_engine::common::Transform;
+
+use super::{
+    collision::PreppedCollisionShape, prep_dynamic_shape, prep_rigid_body_shape, world_transform,
+    CollisionLayers, CollisionShape, DynamicCollisionType, RigidBody, StaticCollisionType,
+    TriggerCollisionType,
+};
+
+//====================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerEvent {
+    Entered { trigger: Entity, other: Entity },
+    Stayed { trigger: Entity, other: Entity },
+    Exited { trigger: Entity, other: Entity },
+}
+
+//====================================================================
+
+pub(super) fn update_triggers(
+    world: &World,
+    overlaps: &mut HashSet<(Entity, Entity)>,
+    events: &mut Vec<TriggerEvent>,
+) {
+    let triggers = world
+        .query::<(&CollisionShape, Option<&CollisionLayers>)>()
+        .with::<&TriggerCollisionType>()
+        .into_iter()
+        .filter_map(|(entity, (shape, layers))| {
+            let transform = world_transform(world, entity)?;
+            let prepped = PreppedCollisionShape::from_collision_shape(
+                shape,
+                transform.translation,
+                transform.rotation,
+                transform.scale,
+            );
+
+            Some((entity, prepped, layers.copied().unwrap_or_default()))
+        })
+        .collect::<Vec<_>>();
+
+    if triggers.is_empty() && overlaps.is_empty() {
+        return;
+    }
+
+    let dynamics = world
+        .query::<(
+            &Transform,
+            &CollisionShape,
+            Option<&CollisionLayers>,
+            Option<&RigidBody>,
+        )>()
+        .with::<&DynamicCollisionType>()
+        .without::<&StaticCollisionType>()
+        .without::<&TriggerCollisionType>()
+        .into_iter()
+        .map(|(entity, (transform, shape, layers, rigid_body))| {
+            let prepped = match rigid_body {
+                Some(_) => prep_rigid_body_shape(shape, transform),
+                None => prep_dynamic_shape(shape, transform.translation, transform),
+            };
+
+            (entity, prepped, layers.copied().unwrap_or_default())
+        })
+        .collect::<Vec<_>>();
+
+    let mut current = triggers
+        .iter()
+        .flat_map(|(trigger, trigger_shape, trigger_layers)| {
+            let trigger_bounds = trigger_shape.bounds();
+
+            dynamics
+                .iter()
+                .filter(move |(_, other_shape, other_layers)| {
+                    trigger_layers.interacts_with(other_layers)
+                        && trigger_bounds.intersects(&other_shape.bounds())
+                        && trigger_shape.check_collision(other_shape)
+                })
+                .map(|(other, _, _)| (*trigger, *other))
+        })
+        .collect::<Vec<_>>();
+
+    current.sort_unstable();
+
+    current.iter().for_each(|(trigger, other)| {
+        let (trigger, other) = (*trigger, *other);
+
+        events.push(match overlaps.remove(&(trigger, other)) {
+            true => TriggerEvent::Stayed { trigger, other },
+            false => TriggerEvent::Entered { trigger, other },
+        });
+    });
+
+    // Anything left over stopped overlapping or was despawned
+    let mut exited = overlaps.drain().collect::<Vec<_>>();
+    exited.sort_unstable();
+
+    events.extend(
+        exited
+            .into_iter()
+            .map(|(trigger, other)| TriggerEvent::Exited { trigger, other }),
+    );
+
+    overlaps.extend(current);
+}
+
+//====================================================================
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{
+        tests::{cube, spawn_mover, TICK},
+        PhysicsHandler,
+    };
+
+    fn spawn_trigger(world: &mut World, translation: glam::Vec3) -> Entity {
+        world.spawn((
+            Transform::from_translation(translation),
+            TriggerCollisionType,
+            cube(20.),
+        ))
+    }
+
+    fn tick_events(physics: &mut PhysicsHandler, world: &mut World) -> Vec<TriggerEvent> {
+        physics.tick(world, TICK);
+        physics.drain_trigger_events().collect()
+    }
+
+    #[test]
+    fn enter_stay_exit() {
+        let mut world = World::new();
+        let trigger = spawn_trigger(&mut world, glam::vec3(100., 0., 0.));
+        let other = spawn_mover(&mut world, glam::Vec3::ZERO, glam::vec3(600., 0., 0.));
+
+        let mut physics = PhysicsHandler::default();
+        let events = (0..30)
+            .flat_map(|_| tick_events(&mut physics, &mut world))
+            .collect::<Vec<_>>();
+
+        let stayed = events.len() - 2;
+        assert!(stayed > 0);
+        assert_eq!(events[0], TriggerEvent::Entered { trigger, other });
+        assert!(events[1..=stayed]
+            .iter()
+            .all(|event| *event == TriggerEvent::Stayed { trigger, other }));
+        assert_eq!(events[stayed + 1], TriggerEvent::Exited { trigger, other });
+    }
+
+    #[test]
+    fn exit_on_despawn() {
+        let mut world = World::new();
+        let trigger = spawn_trigger(&mut world, glam::Vec3::ZERO);
+        let other = spawn_mover(&mut world, glam::Vec3::ZERO, glam::Vec3::ZERO);
+
+        let mut physics = PhysicsHandler::default();
+        assert_eq!(
+            tick_events(&mut physics, &mut world),
+            vec![TriggerEvent::Entered { trigger, other }]
+        );
+
+        world.despawn(other).unwrap();
+        assert_eq!(
+            tick_events(&mut physics, &mut world),
+            vec![TriggerEvent::Exited { trigger, other }]
+        );
+        assert!(tick_events(&mut physics, &mut world).is_empty());
+    }
+}
@@ -9,8 +9,8 @@ use hecs_engine::{
 
 use crate::{
     physics::{
-        Accel, CharacterCollisionBundle, CharacterController, CharacterMovementBundle,
-        CollisionShape,
+        Accel, AirControl, AirJumps, CharacterCollisionBundle, CharacterController,
+        CharacterMovementBundle, CollisionShape, CrouchControl, MaxSlopeAngle, MaxStepHeight,
     },
     Resources,
 };
@@ -29,6 +29,8 @@ pub struct PlayerSpeed {
 pub struct PlayerState {
     player: Entity,
     pub movement_disabled: bool,
+    jump_held: bool,
+    crouch_held: bool,
 
     camera_anchor: Entity,
     camera_angle: f32,
@@ -63,6 +65,17 @@ impl PlayerState {
                     half_height: 20.,
                     half_depth: 10.,
                 }))
+                .add(MaxStepHeight(8.))
+                .add(MaxSlopeAngle(45_f32.to_radians()))
+                .add(AirJumps(1))
+                .add(AirControl {
+                    accel: 800.,
+                    damping: 1.8,
+                })
+                .add(CrouchControl {
+                    height: 0.5,
+                    speed: 0.4,
+                })
                 .build(),
         );
 
@@ -82,6 +95,8 @@ impl PlayerState {
         Self {
             player,
             movement_disabled: false,
+            jump_held: false,
+            crouch_held: false,
 
             camera_anchor,
             camera_angle,
@@ -146,8 +161,16 @@ impl PlayerState {
         let move_dir = glam::Vec3::new(x_dir, 0., z_dir).normalize_or_zero();
 
         let jump = state.keys().pressed(KeyCode::Space);
+        let jump_pressed = jump && !self.jump_held;
+        let jump_released = !jump && self.jump_held;
+        self.jump_held = jump;
         let sprint = state.keys().pressed(KeyCode::ShiftLeft);
 
+        let crouch = state.keys().pressed(KeyCode::ControlLeft);
+        let crouch_pressed = crouch && !self.crouch_held;
+        let crouch_released = !crouch && self.crouch_held;
+        self.crouch_held = crouch;
+
         //--------------------------------------------------
 
         state
@@ -180,12 +203,35 @@ impl PlayerState {
 
         transform.rotation = yaw_rotation * transform.rotation;
 
-        if jump {
+        if jump_pressed {
             controller
                 .movement_action_queue
                 .push(crate::physics::MovementAction::Jump);
         }
 
+        if jump_released {
+            controller
+                .movement_action_queue
+                .push(crate::physics::MovementAction::JumpRelease);
+        }
+
+        if crouch_pressed {
+            controller
+                .movement_action_queue
+                .push(crate::physics::MovementAction::Crouch);
+        }
+
+        if crouch_released {
+            controller
+                .movement_action_queue
+                .push(crate::physics::MovementAction::CrouchRelease);
+        }
+
+        // Squash the model to match the collider while crouched
+        if let CollisionShape::Box { half_height, .. } = *player.get::<&CollisionShape>().unwrap() {
+            player.get::<&mut Model>().unwrap().scale.y = half_height * 2.;
+        }
+
         let mut accel = player.get::<&mut Accel>().unwrap();
         let speed = player.get::<&PlayerSpeed>().unwrap();
 
@@ -0,0 +1,77 @@
+//====================================================================
+
+use std::sync::Arc;
+
+use hecs::Entity;
+use hecs_engine::{
+    common::{GlobalTransform, Transform},
+    engine::State,
+    pipelines::model_renderer::{Mesh, Model},
+    renderer::shared::ModelVertex,
+};
+
+use crate::{
+    physics::{CollisionShape, Heightfield, StaticCollisionType},
+    Resources,
+};
+
+//====================================================================
+
+const TERRAIN_SIZE: usize = 33;
+const TERRAIN_CELL_SIZE: f32 = 10.;
+const TERRAIN_HILL_HEIGHT: f32 = 60.;
+
+//====================================================================
+
+// Rolling hills that start level with the floor along their first row
+pub fn spawn_terrain(state: &mut State, resources: &Resources, translation: glam::Vec3) -> Entity {
+    let heightfield = Arc::new(Heightfield::from_fn(
+        TERRAIN_SIZE,
+        TERRAIN_SIZE,
+        TERRAIN_CELL_SIZE,
+        |column, row| {
+            let x = column as f32 / (TERRAIN_SIZE - 1) as f32;
+            let z = row as f32 / (TERRAIN_SIZE - 1) as f32;
+
+            let hills = (x * std::f32::consts::TAU * 1.5).sin() * 0.5 + 0.5;
+            z * z * TERRAIN_HILL_HEIGHT * (0.4 + hills * 0.6)
+        },
+    ));
+
+    let mesh = Arc::new(load_heightfield_mesh(state, &heightfield));
+
+    state.world_mut().spawn((
+        Transform::from_translation(translation),
+        GlobalTransform::default(),
+        Model {
+            meshes: vec![(mesh, resources.texture.clone())],
+            color: [0.35, 0.6, 0.3, 1.],
+            scale: glam::Vec3::ONE,
+        },
+        StaticCollisionType,
+        CollisionShape::Heightfield(heightfield),
+    ))
+}
+
+// Render mesh built from the same triangles the collider uses. Meshes have 16 bit
+// indices so the grid can't have more points than they can address.
+pub fn load_heightfield_mesh(state: &State, heightfield: &Heightfield) -> Mesh {
+    assert!(
+        heightfield.columns() * heightfield.rows() <= u16::MAX as usize + 1,
+        "heightfield too large for 16 bit mesh indices"
+    );
+
+    let (vertices, indices) = heightfield.mesh_buffers(|position, _, uv| ModelVertex {
+        pos: position.into(),
+        uv: uv.into(),
+    });
+
+    let indices = indices
+        .into_iter()
+        .map(|index| index as u16)
+        .collect::<Vec<_>>();
+
+    Mesh::load_mesh(state.renderer().core().device(), &vertices, &indices)
+}
+
+//====================================================================